@@ -0,0 +1,44 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use lesspass::{generate_entropy_to, generate_salt, Algorithm, MasterKey};
+
+const ITERATIONS: u32 = 10_000;
+
+fn pbkdf2(c: &mut Criterion) {
+    let salts = (0..8)
+        .map(|i| generate_salt(&format!("example{i}.org"), "contact@example.org", 1))
+        .collect::<Vec<_>>();
+    let key = MasterKey::new("password", Algorithm::SHA256);
+    let mut entropies = [[0; 32]; 8];
+    let mut group = c.benchmark_group("pbkdf2_sha256_8_salts");
+
+    group.bench_function("generate_entropy_to", |b| {
+        b.iter(|| {
+            for (salt, entropy) in salts.iter().zip(&mut entropies) {
+                generate_entropy_to(
+                    black_box("password"),
+                    salt,
+                    Algorithm::SHA256,
+                    ITERATIONS,
+                    entropy,
+                );
+            }
+        })
+    });
+
+    group.bench_function("master_key", |b| {
+        b.iter(|| {
+            for (salt, entropy) in salts.iter().zip(&mut entropies) {
+                key.generate_entropy_to(salt, ITERATIONS, entropy);
+            }
+        })
+    });
+
+    group.bench_function("master_key_batch", |b| {
+        b.iter(|| key.generate_entropy_batch_to(&salts, ITERATIONS, &mut entropies))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, pbkdf2);
+criterion_main!(benches);
@@ -0,0 +1,51 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use lesspass::{
+    extended_entropy_len, generate_entropy, generate_extended_entropy_to, generate_salt,
+    render_extended_password_to, render_password_to, Algorithm, CharacterSet,
+    MAX_EXTENDED_PASSWORD_LEN, MAX_PASSWORD_LEN,
+};
+
+fn render(c: &mut Criterion) {
+    let salt = generate_salt("example.org", "contact@example.org", 1);
+    let entropy = generate_entropy("password", &salt, Algorithm::SHA256, 1);
+    let mut group = c.benchmark_group("render_password");
+
+    for (name, charset) in [
+        ("all", CharacterSet::All),
+        ("letters", CharacterSet::Letters),
+        ("digits", CharacterSet::Digits),
+    ] {
+        for len in [16, MAX_PASSWORD_LEN] {
+            let mut output = vec![0; len];
+
+            group.bench_with_input(BenchmarkId::new(name, len), &len, |b, _| {
+                b.iter(|| render_password_to(black_box(&entropy), charset, &mut output))
+            });
+        }
+    }
+
+    group.finish();
+}
+
+fn render_extended(c: &mut Criterion) {
+    let salt = generate_salt("example.org", "contact@example.org", 1);
+    let mut group = c.benchmark_group("render_extended_password");
+
+    for len in [64, MAX_EXTENDED_PASSWORD_LEN] {
+        let mut entropy = vec![0; extended_entropy_len(CharacterSet::All, len)];
+        let mut output = vec![0; len];
+
+        generate_extended_entropy_to("password", &salt, Algorithm::SHA256, 1, &mut entropy);
+
+        group.bench_with_input(BenchmarkId::new("all", len), &len, |b, _| {
+            b.iter(|| {
+                render_extended_password_to(black_box(&entropy), CharacterSet::All, &mut output)
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, render, render_extended);
+criterion_main!(benches);
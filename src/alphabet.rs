@@ -0,0 +1,278 @@
+use crate::{ct, CharacterSet, Error};
+
+/// The maximum number of classes in an [`Alphabet`].
+pub const MAX_ALPHABET_CLASSES: usize = 8;
+
+/// Characters that are easily confused with one another when read from a
+/// screen, and which are removed by [`Alphabet::without_ambiguous`].
+pub const AMBIGUOUS_CHARACTERS: &str = "0Oo1Il|'\"`";
+
+/// A list of classes of characters that may be used to render a password.
+///
+/// Like with [`CharacterSet`], a rendered password will always contain at
+/// least one character of each class. Classes are user-defined, which allows
+/// for instance to only use some symbols:
+///
+/// ```
+/// use lesspass::{Alphabet, CharacterSet};
+///
+/// let alphabet = Alphabet::new(&[
+///     CharacterSet::LOWERCASE,
+///     CharacterSet::UPPERCASE,
+///     CharacterSet::DIGITS,
+///     "!@#$%",
+/// ])
+/// .unwrap();
+/// # #[cfg(feature = "std")]
+/// # assert!(lesspass::render_password(&[42; 32], alphabet, 16)
+/// #     .bytes()
+/// #     .any(|c| b"!@#$%".contains(&c)));
+/// ```
+///
+/// Every function that accepts a [`CharacterSet`] also accepts an
+/// [`Alphabet`]; a [`CharacterSet`] is equivalent to the alphabet returned by
+/// [`Alphabet::from_charset`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Alphabet<'a> {
+    classes: [&'a str; MAX_ALPHABET_CLASSES],
+    classes_len: usize,
+    exclude_ambiguous: bool,
+}
+
+impl<'a> Alphabet<'a> {
+    /// Creates an alphabet with the given classes of characters.
+    ///
+    /// Returns an [`Error`] if there are no classes or more than
+    /// [`MAX_ALPHABET_CLASSES`], or if a class is empty, contains a character
+    /// that is not printable ASCII, or contains a character that appears
+    /// earlier in the alphabet (in the same class or in a previous one).
+    pub fn new(classes: &[&'a str]) -> Result<Self, Error> {
+        if classes.is_empty() {
+            return Err(Error::EmptyCharset);
+        }
+        if classes.len() > MAX_ALPHABET_CLASSES {
+            return Err(Error::TooManyClasses {
+                max: MAX_ALPHABET_CLASSES,
+                got: classes.len(),
+            });
+        }
+
+        let mut seen = [false; 128];
+
+        for (index, class) in classes.iter().enumerate() {
+            if class.is_empty() {
+                return Err(Error::EmptyClass { index });
+            }
+            if !class.bytes().all(|c| c == b' ' || c.is_ascii_graphic()) {
+                return Err(Error::InvalidClassCharacter { index });
+            }
+
+            for c in class.bytes() {
+                if core::mem::replace(&mut seen[usize::from(c)], true) {
+                    return Err(Error::DuplicateCharacter { index });
+                }
+            }
+        }
+
+        let mut alphabet = Self {
+            classes: [""; MAX_ALPHABET_CLASSES],
+            classes_len: classes.len(),
+            exclude_ambiguous: false,
+        };
+
+        alphabet.classes[..classes.len()].copy_from_slice(classes);
+
+        Ok(alphabet)
+    }
+
+    /// Returns the alphabet that corresponds to the given [`CharacterSet`],
+    /// with classes in the order given by [`CharacterSet::get_sets`].
+    pub const fn from_charset(charset: CharacterSet) -> Alphabet<'static> {
+        let (sets, sets_len) = charset.get_sets();
+        let mut classes = [""; MAX_ALPHABET_CLASSES];
+        let mut i = 0;
+
+        while i < sets_len {
+            classes[i] = sets[i];
+            i += 1;
+        }
+
+        Alphabet {
+            classes,
+            classes_len: sets_len,
+            exclude_ambiguous: false,
+        }
+    }
+
+    /// Returns the same alphabet, without the characters in
+    /// [`AMBIGUOUS_CHARACTERS`] (such as `l`, `1` and `I`).
+    ///
+    /// Note that rendering a password will fail with [`Error::EmptyClass`] if
+    /// a class only contains ambiguous characters.
+    pub const fn without_ambiguous(mut self) -> Self {
+        self.exclude_ambiguous = true;
+        self
+    }
+
+    /// Returns whether ambiguous characters are excluded from the alphabet.
+    #[inline]
+    pub const fn excludes_ambiguous(&self) -> bool {
+        self.exclude_ambiguous
+    }
+
+    /// Returns the classes of characters of the alphabet, as given when
+    /// creating it (i.e. including ambiguous characters).
+    #[inline]
+    pub fn classes(&self) -> &[&'a str] {
+        &self.classes[..self.classes_len]
+    }
+
+    /// Returns whether the alphabet has no characters, i.e. if it has no
+    /// classes or if all of its characters are excluded.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the total number of characters in the alphabet.
+    pub fn len(&self) -> usize {
+        (0..self.classes_len).map(|i| self.class_len(i)).sum()
+    }
+
+    /// Returns the number of characters in the class at the given index.
+    #[inline]
+    pub fn class_len(&self, class: usize) -> usize {
+        if self.exclude_ambiguous {
+            self.class_chars(class).count()
+        } else {
+            self.classes[class].len()
+        }
+    }
+
+    /// Returns the character at the given index in the class at index `class`.
+    ///
+    /// `index` must be smaller than [`Alphabet::class_len`]. All characters of
+    /// the class are read, so that the running time does not depend on
+    /// `index`.
+    #[inline]
+    pub(crate) fn class_char_at(&self, class: usize, index: usize) -> u8 {
+        debug_assert!(index < self.class_len(class));
+
+        self.class_chars(class)
+            .enumerate()
+            .fold(0, |result, (i, c)| {
+                ct::select(ct::eq_mask(i, index), c, result)
+            })
+    }
+
+    /// Returns the character at the given index in the concatenation of all
+    /// classes.
+    ///
+    /// `index` must be smaller than [`Alphabet::len`]. All characters of the
+    /// alphabet are read, so that the running time does not depend on
+    /// `index`.
+    #[inline]
+    pub(crate) fn char_at(&self, index: usize) -> u8 {
+        debug_assert!(index < self.len());
+
+        (0..self.classes_len)
+            .flat_map(|class| self.class_chars(class))
+            .enumerate()
+            .fold(0, |result, (i, c)| {
+                ct::select(ct::eq_mask(i, index), c, result)
+            })
+    }
+
+    /// Returns an iterator over the characters of the class at the given
+    /// index which are not excluded.
+    #[inline]
+    fn class_chars(&self, class: usize) -> impl Iterator<Item = u8> + '_ {
+        let exclude_ambiguous = self.exclude_ambiguous;
+
+        self.classes[class]
+            .bytes()
+            .filter(move |c| !exclude_ambiguous || !AMBIGUOUS_CHARACTERS.as_bytes().contains(c))
+    }
+}
+
+impl From<CharacterSet> for Alphabet<'static> {
+    #[inline]
+    fn from(charset: CharacterSet) -> Self {
+        Self::from_charset(charset)
+    }
+}
+
+impl<'a, 'b> From<&'b Alphabet<'a>> for Alphabet<'a> {
+    #[inline]
+    fn from(alphabet: &'b Alphabet<'a>) -> Self {
+        *alphabet
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_charset() {
+        for bits in 1..=CharacterSet::All.bits() {
+            let charset = CharacterSet::from_bits_truncate(bits);
+            let alphabet = Alphabet::from_charset(charset);
+            let chars = charset.get_characters().as_bytes();
+
+            assert_eq!(alphabet.len(), chars.len());
+
+            for (i, &c) in chars.iter().enumerate() {
+                assert_eq!(alphabet.char_at(i), c);
+            }
+        }
+    }
+
+    #[test]
+    fn without_ambiguous() {
+        let alphabet = Alphabet::from_charset(CharacterSet::All).without_ambiguous();
+        let chars = (0..alphabet.len())
+            .map(|i| alphabet.char_at(i))
+            .collect::<std::vec::Vec<_>>();
+
+        assert_eq!(alphabet.len(), 94 - AMBIGUOUS_CHARACTERS.len());
+        assert_eq!(alphabet.class_len(2), 8);
+        assert!(!alphabet.is_empty());
+        assert!(Alphabet::new(&["0O", "1l"])
+            .unwrap()
+            .without_ambiguous()
+            .is_empty());
+        assert!(!chars
+            .iter()
+            .any(|c| AMBIGUOUS_CHARACTERS.as_bytes().contains(c)));
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(Alphabet::new(&[]), Err(Error::EmptyCharset));
+        assert_eq!(
+            Alphabet::new(&["a"; 9]),
+            Err(Error::TooManyClasses { max: 8, got: 9 }),
+        );
+        assert_eq!(
+            Alphabet::new(&["abc", ""]),
+            Err(Error::EmptyClass { index: 1 }),
+        );
+        assert_eq!(
+            Alphabet::new(&["abc", "é"]),
+            Err(Error::InvalidClassCharacter { index: 1 }),
+        );
+        assert_eq!(
+            Alphabet::new(&["abc", "\t"]),
+            Err(Error::InvalidClassCharacter { index: 1 }),
+        );
+        assert_eq!(
+            Alphabet::new(&["abca", "01"]),
+            Err(Error::DuplicateCharacter { index: 0 }),
+        );
+        assert_eq!(
+            Alphabet::new(&[CharacterSet::LOWERCASE, "!@#$%", "z"]),
+            Err(Error::DuplicateCharacter { index: 2 }),
+        );
+    }
+}
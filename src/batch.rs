@@ -0,0 +1,316 @@
+//! Generation of the passwords of many profiles in parallel.
+
+use std::collections::BTreeMap;
+use std::vec::Vec;
+
+use zeroize::Zeroizing;
+
+use crate::lanes::MAX_LANES;
+use crate::{Algorithm, Entropy, Error, Kdf, MasterKey, Password, Profile};
+
+/// Generates the passwords of all the given profiles with the same master
+/// password, using all available threads.
+///
+/// Since deriving entropy is by far the most expensive step of generating a
+/// password, this is much faster than calling [`Profile::try_generate`] for
+/// each profile when there are many of them. Profiles using
+/// [`Algorithm::SHA256`] with the same number of iterations are additionally
+/// derived several at a time with
+/// [`MasterKey::generate_entropy_batch_to`].
+///
+/// Results are returned in the same order as `profiles`; each of them is the
+/// result of [`Profile::try_generate`] for the corresponding profile.
+///
+/// Threads are spawned with [`std::thread::scope`], or taken from the global
+/// thread pool of [`rayon`](https://docs.rs/rayon) if the `rayon` feature is
+/// enabled.
+///
+/// ```
+/// use lesspass::{generate_batch, Profile};
+///
+/// let profiles = ["example.org", "example.com", "example.net"]
+///     .map(|website| Profile::new(website, "contact@example.org").with_iterations(1_000));
+/// let passwords = generate_batch(&profiles, "password");
+///
+/// for (profile, password) in profiles.iter().zip(passwords) {
+///     assert_eq!(
+///         password.unwrap().as_str(),
+///         profile.generate("password").as_str(),
+///     );
+/// }
+/// ```
+pub fn generate_batch<K: Kdf + Sync>(
+    profiles: &[Profile<'_, K>],
+    master_password: &str,
+) -> Vec<Result<Password, Error>> {
+    let Ok(key) = MasterKey::try_new(master_password, Algorithm::SHA256) else {
+        return map_parallel(profiles, |profile| profile.try_generate(master_password));
+    };
+
+    let tasks = split_tasks(profiles);
+    let task_results = map_parallel(&tasks, |task| {
+        let profiles = task.iter().map(|&index| &profiles[index]);
+
+        if task.len() == 1 {
+            profiles
+                .map(|profile| profile.try_generate(master_password))
+                .collect()
+        } else {
+            generate_lanes(&key, &profiles.collect::<Vec<_>>(), master_password)
+        }
+    });
+
+    let mut results = Vec::with_capacity(profiles.len());
+
+    results.resize_with(profiles.len(), || None);
+
+    for (task, task_results) in tasks.iter().zip(task_results) {
+        for (&index, result) in task.iter().zip(task_results) {
+            results[index] = Some(result);
+        }
+    }
+
+    results
+        .into_iter()
+        .map(|result| result.expect("all profiles were processed"))
+        .collect()
+}
+
+/// Splits `profiles` into tasks, each of them being a list of indices in
+/// `profiles`.
+///
+/// Tasks with more than one profile only contain valid profiles (with a
+/// supported length and counter) using SHA-256 with the same number of
+/// iterations, whose entropy can be derived at once by
+/// [`MasterKey::try_generate_entropy_batch_to`].
+fn split_tasks<K: Kdf>(profiles: &[Profile<'_, K>]) -> Vec<Vec<usize>> {
+    let mut tasks = Vec::new();
+    let mut groups = BTreeMap::<u32, Vec<usize>>::new();
+
+    for (index, profile) in profiles.iter().enumerate() {
+        if profile.kdf().as_algorithm() == Some(Algorithm::SHA256)
+            && profile.iterations() != 0
+            && profile.check_length().is_ok()
+            && profile.check_counter(profile.counter()).is_ok()
+        {
+            groups.entry(profile.iterations()).or_default().push(index);
+        } else {
+            tasks.push(std::vec![index]);
+        }
+    }
+
+    // Lanes are only useful as long as they do not leave threads idle.
+    let threads = available_threads();
+
+    for group in groups.values() {
+        let lanes = ((group.len() + threads - 1) / threads).clamp(1, MAX_LANES);
+
+        tasks.extend(group.chunks(lanes).map(<[usize]>::to_vec));
+    }
+
+    tasks
+}
+
+/// Generates the passwords of profiles which all use SHA-256 with the same
+/// number of iterations, deriving their entropy at once.
+fn generate_lanes<K: Kdf>(
+    key: &MasterKey,
+    profiles: &[&Profile<'_, K>],
+    master_password: &str,
+) -> Vec<Result<Password, Error>> {
+    let salts = profiles
+        .iter()
+        .map(|profile| Zeroizing::new(profile.salt(profile.counter())))
+        .collect::<Vec<_>>();
+    let mut entropies = profiles
+        .iter()
+        .map(|profile| Entropy::zeroed(profile.entropy_len()))
+        .collect::<Vec<_>>();
+    let mut outputs = entropies
+        .iter_mut()
+        .map(Entropy::as_mut_bytes)
+        .collect::<Vec<_>>();
+
+    if key
+        .try_generate_entropy_batch_to(&salts, profiles[0].iterations(), &mut outputs)
+        .is_err()
+    {
+        // Let each profile report its own error.
+        return profiles
+            .iter()
+            .map(|profile| profile.try_generate(master_password))
+            .collect();
+    }
+
+    profiles
+        .iter()
+        .zip(&entropies)
+        .map(|(profile, entropy)| profile.render(entropy))
+        .collect()
+}
+
+/// Returns the number of threads used by [`map_parallel`].
+#[cfg(feature = "rayon")]
+fn available_threads() -> usize {
+    rayon::current_num_threads()
+}
+
+/// Returns the number of threads used by [`map_parallel`].
+#[cfg(not(feature = "rayon"))]
+fn available_threads() -> usize {
+    std::thread::available_parallelism().map_or(1, |threads| threads.get())
+}
+
+/// Returns `items.iter().map(f).collect()`, calling `f` on several threads.
+#[cfg(feature = "rayon")]
+fn map_parallel<I: Sync, T: Send>(items: &[I], f: impl Fn(&I) -> T + Sync) -> Vec<T> {
+    use rayon::prelude::*;
+
+    items.par_iter().map(&f).collect()
+}
+
+/// Returns `items.iter().map(f).collect()`, calling `f` on several threads.
+#[cfg(not(feature = "rayon"))]
+fn map_parallel<I: Sync, T: Send>(items: &[I], f: impl Fn(&I) -> T + Sync) -> Vec<T> {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    let threads = available_threads().min(items.len());
+
+    if threads <= 1 {
+        return items.iter().map(f).collect();
+    }
+
+    // Items are picked one at a time by each thread, so that threads which
+    // are done early do not stay idle while others still have work.
+    let next = AtomicUsize::new(0);
+    let work = || {
+        let mut results = Vec::new();
+
+        loop {
+            let index = next.fetch_add(1, Ordering::Relaxed);
+            let Some(item) = items.get(index) else {
+                return results;
+            };
+
+            results.push((index, f(item)));
+        }
+    };
+
+    let mut results = Vec::with_capacity(items.len());
+
+    results.resize_with(items.len(), || None);
+
+    thread::scope(|scope| {
+        let workers = (0..threads).map(|_| scope.spawn(work)).collect::<Vec<_>>();
+
+        for worker in workers {
+            let worker_results = worker
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+
+            for (index, result) in worker_results {
+                results[index] = Some(result);
+            }
+        }
+    });
+
+    results
+        .into_iter()
+        .map(|result| result.expect("all items were processed"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Algorithm, CharacterSet};
+
+    #[test]
+    fn preserves_order() {
+        let websites = (0..20)
+            .map(|i| std::format!("example{i}.org"))
+            .collect::<Vec<_>>();
+        let profiles = websites
+            .iter()
+            .enumerate()
+            .map(|(i, website)| {
+                Profile::new(website, "contact@example.org")
+                    .with_iterations(100 + i as u32)
+                    .with_length(5 + i)
+            })
+            .collect::<Vec<_>>();
+        let passwords = generate_batch(&profiles, "password");
+
+        assert_eq!(passwords.len(), profiles.len());
+
+        for (profile, password) in profiles.iter().zip(passwords) {
+            assert_eq!(
+                password.unwrap().as_str(),
+                profile.generate("password").as_str(),
+            );
+        }
+    }
+
+    #[test]
+    fn lanes() {
+        let profiles = (0..20)
+            .map(|i| {
+                Profile::new("example.org", "contact@example.org")
+                    .with_counter(i)
+                    .with_iterations(100)
+                    .with_length(5 + 7 * i as usize)
+                    .with_extended(true)
+            })
+            .chain([Profile::new("example.org", "")
+                .with_algorithm(Algorithm::SHA384)
+                .with_iterations(10)])
+            .collect::<Vec<_>>();
+        let passwords = generate_batch(&profiles, "password");
+
+        assert_eq!(passwords.len(), profiles.len());
+
+        for (profile, password) in profiles.iter().zip(passwords) {
+            assert_eq!(
+                password.unwrap().as_str(),
+                profile.generate("password").as_str(),
+            );
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let profile = Profile::new("example.org", "contact@example.org").with_iterations(1);
+        let profiles = [
+            profile,
+            profile.with_iterations(0),
+            profile.with_algorithm(Algorithm::SHA512),
+            profile.with_charset(CharacterSet::Digits).with_length(36),
+            profile
+                .with_counter(crate::MAX_STRICT_COUNTER + 5)
+                .with_strict(true),
+        ];
+        let results = generate_batch(&profiles, "password");
+
+        assert!(results[0].is_ok());
+        assert_eq!(results[1].as_ref().err(), Some(&Error::ZeroIterations));
+        assert!(results[2].is_ok());
+        assert!(matches!(
+            results[3],
+            Err(Error::LengthOutOfRange { got: 36, .. })
+        ));
+        assert_eq!(
+            results[4].as_ref().err(),
+            Some(&Error::CounterOutOfRange {
+                max: crate::MAX_STRICT_COUNTER,
+                got: crate::MAX_STRICT_COUNTER + 5,
+            }),
+        );
+        assert!(split_tasks(&profiles).contains(&std::vec![4]));
+
+        assert!(generate_batch::<Algorithm>(&[], "password").is_empty());
+        assert!(generate_batch(&[profile, profile], "")
+            .iter()
+            .all(|result| result.as_ref().err() == Some(&Error::EmptyMasterPassword)));
+    }
+}
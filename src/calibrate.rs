@@ -0,0 +1,131 @@
+//! Calibration of the number of iterations of PBKDF2 on the current machine.
+
+use std::time::{Duration, Instant};
+
+use crate::{error, try_generate_entropy_to, Algorithm, Error, MAX_ENTROPY_LEN};
+
+/// The longest time spent measuring a single derivation.
+const MAX_SAMPLE_TIME: Duration = Duration::from_millis(200);
+
+/// The number of iterations of the first measured derivation.
+const INITIAL_ITERATIONS: u32 = 1_000;
+
+/// Returns the number of iterations with which deriving entropy with
+/// [`generate_entropy_to`](crate::generate_entropy_to) and the given
+/// algorithm takes about `target` on the current machine.
+///
+/// Derivations with an increasing number of iterations are timed until one
+/// of them takes long enough to be measured reliably, and the result is
+/// extrapolated from it; this takes at most a few hundred milliseconds. The
+/// result is at least 1, and is only an estimate since it depends on the
+/// load of the machine.
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use lesspass::{calibrate_iterations, Algorithm};
+///
+/// let iterations = calibrate_iterations(Algorithm::SHA256, Duration::from_millis(10));
+///
+/// assert!(iterations >= 1);
+/// ```
+///
+/// # Panics
+///
+/// Panics if `algorithm` does not use PBKDF2, since other algorithms ignore
+/// the number of iterations. See [`try_calibrate_iterations`] for a version
+/// of this function that returns an [`Error`] instead.
+#[track_caller]
+#[inline]
+pub fn calibrate_iterations(algorithm: Algorithm, target: Duration) -> u32 {
+    error::unwrap(try_calibrate_iterations(algorithm, target))
+}
+
+/// Same as [`calibrate_iterations`], but returns an [`Error`] instead of
+/// panicking if `algorithm` does not use PBKDF2.
+pub fn try_calibrate_iterations(algorithm: Algorithm, target: Duration) -> Result<u32, Error> {
+    if !algorithm.is_pbkdf2() {
+        return Err(Error::UnsupportedAlgorithm);
+    }
+
+    let sample_time = target.min(MAX_SAMPLE_TIME);
+    let mut output = [0; MAX_ENTROPY_LEN];
+    let output = &mut output[..algorithm.entropy_len()];
+    let mut iterations = INITIAL_ITERATIONS;
+
+    loop {
+        let start = Instant::now();
+
+        try_generate_entropy_to("lesspass", b"calibration", algorithm, iterations, output)?;
+
+        let elapsed = start.elapsed();
+
+        if elapsed >= sample_time || iterations == u32::MAX {
+            return Ok(extrapolate(iterations, elapsed, target));
+        }
+
+        iterations = iterations.saturating_mul(2);
+    }
+}
+
+/// Returns the number of iterations that take `target`, given that
+/// `iterations` took `elapsed`. The result is at least 1.
+fn extrapolate(iterations: u32, elapsed: Duration, target: Duration) -> u32 {
+    let estimate = f64::from(iterations) * target.as_secs_f64() / elapsed.as_secs_f64();
+
+    // Float to integer casts saturate, and NaN is cast to 0.
+    (estimate as u32).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calibrate() {
+        let short = calibrate_iterations(Algorithm::SHA256, Duration::from_millis(1));
+        let long = calibrate_iterations(Algorithm::SHA256, Duration::from_millis(200));
+
+        // The targets are far enough apart for the load of the machine not to
+        // matter.
+        assert!(long > 10 * short, "{long} <= 10 * {short}");
+        assert_eq!(calibrate_iterations(Algorithm::SHA256, Duration::ZERO), 1);
+        assert_eq!(calibrate_iterations(Algorithm::SHA512, Duration::ZERO), 1);
+    }
+
+    #[test]
+    fn extrapolation() {
+        let ms = Duration::from_millis;
+
+        assert_eq!(extrapolate(1_000, ms(10), ms(100)), 10_000);
+        assert_eq!(extrapolate(64_000, ms(200), ms(50)), 16_000);
+        assert_eq!(extrapolate(1_000, ms(10), ms(1)), 100);
+        assert_eq!(extrapolate(1_000, ms(200), Duration::from_micros(1)), 1);
+        assert_eq!(extrapolate(u32::MAX, ms(1), ms(10)), u32::MAX);
+
+        // Derivations may be too fast to be measured.
+        assert_eq!(extrapolate(1_000, Duration::ZERO, Duration::ZERO), 1);
+        assert_eq!(extrapolate(1_000, Duration::ZERO, ms(1)), u32::MAX);
+    }
+
+    #[test]
+    #[cfg(any(feature = "argon2", feature = "scrypt"))]
+    fn unsupported() {
+        #[cfg(feature = "argon2")]
+        assert_eq!(
+            try_calibrate_iterations(
+                Algorithm::Argon2id(crate::Argon2Params::new(64, 1, 1)),
+                Duration::from_millis(5),
+            ),
+            Err(Error::UnsupportedAlgorithm),
+        );
+        #[cfg(feature = "scrypt")]
+        assert_eq!(
+            try_calibrate_iterations(
+                Algorithm::Scrypt(crate::ScryptParams::new(4, 1, 1)),
+                Duration::from_millis(5),
+            ),
+            Err(Error::UnsupportedAlgorithm),
+        );
+    }
+}
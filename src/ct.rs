@@ -0,0 +1,357 @@
+//! Constant-time helpers used to render passwords.
+//!
+//! The entropy used to render a password is secret, so rendering must not
+//! branch or access memory depending on its value. Divisors (i.e. the lengths
+//! of the alphabet, of its classes, and of the password) are public, and may
+//! be used freely.
+
+use core::hint::black_box;
+use core::iter::Peekable;
+
+use zeroize::Zeroize;
+
+use crate::MAX_EXTENDED_ENTROPY_LEN;
+
+/// The maximum number of 32-bit limbs in a [`Quotient`].
+const MAX_LIMBS: usize = (MAX_EXTENDED_ENTROPY_LEN + 3) / 4;
+
+/// A big-endian number that is repeatedly divided by small divisors, in
+/// constant time with respect to its value.
+pub(crate) struct Quotient {
+    limbs: [u32; MAX_LIMBS],
+    len: usize,
+}
+
+impl Quotient {
+    /// Creates a quotient from the given big-endian bytes, which must be at
+    /// most [`MAX_EXTENDED_ENTROPY_LEN`] bytes long.
+    pub(crate) fn from_be_bytes(bytes: &[u8]) -> Self {
+        debug_assert!(bytes.len() <= MAX_EXTENDED_ENTROPY_LEN);
+
+        let len = (bytes.len() + 3) / 4;
+        let mut quotient = Self {
+            limbs: [0; MAX_LIMBS],
+            len,
+        };
+
+        // Bytes are stored starting from the end so that the first limb is
+        // padded with leading zeros.
+        for (i, &byte) in bytes.iter().rev().enumerate() {
+            quotient.limbs[len - 1 - i / 4] |= u32::from(byte) << (8 * (i % 4));
+        }
+
+        quotient
+    }
+
+    /// Updates the quotient in place after dividing it by `div`, and returns
+    /// the remainder.
+    ///
+    /// `div` must be in the `1..=u32::MAX` range.
+    #[inline]
+    pub(crate) fn div_rem(&mut self, div: usize) -> usize {
+        self.div_rem_by(Divisor::new(div)) as usize
+    }
+
+    /// Same as [`Quotient::div_rem`], but with a precomputed divisor.
+    #[inline]
+    fn div_rem_by(&mut self, div: Divisor) -> u64 {
+        let mut rem = 0u64;
+
+        for limb in &mut self.limbs[..self.len] {
+            // `rem < div <= u32::MAX`, so `current` fits in 64 bits and
+            // `current / div` fits in 32 bits.
+            let (quot, new_rem) = div.div_rem((rem << 32) | u64::from(*limb));
+
+            *limb = quot as u32;
+            rem = new_rem;
+        }
+
+        rem
+    }
+
+    /// Returns whether the quotient is smaller than `x`, without branching on
+    /// its value.
+    pub(crate) fn is_less_than(&self, x: u64) -> bool {
+        let limbs = &self.limbs[..self.len];
+        let (high, low) = limbs.split_at(self.len.saturating_sub(2));
+        let high = high.iter().fold(0, |acc, &limb| acc | limb);
+        let low = low
+            .iter()
+            .fold(0u64, |acc, &limb| (acc << 32) | u64::from(limb));
+        // `low - x` wraps around iff `low < x`.
+        let (_, low_is_less) = low.overflowing_sub(x);
+
+        black_box(high == 0) & low_is_less
+    }
+
+    /// Returns an iterator over the remainders of the successive divisions of
+    /// the quotient by each of the given divisors.
+    ///
+    /// This is equivalent to calling [`Quotient::div_rem`] for each divisor,
+    /// but consecutive divisors are batched: the quotient is divided once by
+    /// their product (as long as it fits in a limb), and the remainders of the
+    /// batch are then extracted from the (single-limb) remainder of this
+    /// division. Since the divisors are public, so are the batches.
+    pub(crate) fn digits<I: Iterator<Item = usize>>(&mut self, divisors: I) -> Digits<'_, I> {
+        Digits {
+            quotient: self,
+            divisors: divisors.peekable(),
+            batch: [Divisor::new(1); MAX_BATCH_LEN],
+            batch_len: 0,
+            batch_pos: 0,
+            rem: 0,
+        }
+    }
+}
+
+impl Drop for Quotient {
+    fn drop(&mut self) {
+        self.limbs.zeroize();
+    }
+}
+
+/// The maximum number of divisors in a batch of [`Digits`], reached when
+/// dividing by 2 since the product of the batch must fit in a limb.
+const MAX_BATCH_LEN: usize = 32;
+
+/// An iterator over the remainders of the successive divisions of a
+/// [`Quotient`], returned by [`Quotient::digits`].
+pub(crate) struct Digits<'q, I: Iterator<Item = usize>> {
+    quotient: &'q mut Quotient,
+    divisors: Peekable<I>,
+    batch: [Divisor; MAX_BATCH_LEN],
+    batch_len: usize,
+    batch_pos: usize,
+    /// The part of the remainder of the division by the product of the batch
+    /// which was not extracted yet.
+    rem: u64,
+}
+
+impl<I: Iterator<Item = usize>> Digits<'_, I> {
+    /// Divides the quotient by the product of as many of the next divisors as
+    /// possible, and returns whether there was at least one divisor left.
+    fn next_batch(&mut self) -> bool {
+        let mut product = 1u64;
+
+        self.batch_len = 0;
+        self.batch_pos = 0;
+
+        while self.batch_len < MAX_BATCH_LEN {
+            let fits = |&div: &usize| {
+                debug_assert!((1..=u32::MAX as usize).contains(&div));
+
+                product * div as u64 <= u64::from(u32::MAX)
+            };
+            let Some(div) = self.divisors.next_if(fits) else {
+                break;
+            };
+
+            // Avoid computing the reciprocal of the same divisor again, which
+            // is the common case when rendering passwords.
+            self.batch[self.batch_len] = match self.batch_len.checked_sub(1) {
+                Some(last) if self.batch[last].div == div as u64 => self.batch[last],
+                _ => Divisor::new(div),
+            };
+            self.batch_len += 1;
+            product *= div as u64;
+        }
+
+        if self.batch_len == 0 {
+            return false;
+        }
+
+        self.rem = self.quotient.div_rem_by(Divisor::new(product as usize));
+
+        true
+    }
+}
+
+impl<I: Iterator<Item = usize>> Iterator for Digits<'_, I> {
+    type Item = usize;
+
+    #[inline]
+    fn next(&mut self) -> Option<usize> {
+        if self.batch_pos == self.batch_len && !self.next_batch() {
+            return None;
+        }
+
+        // `rem` is smaller than the product of the remaining divisors of the
+        // batch, which fits in a limb.
+        let (quot, rem) = self.batch[self.batch_pos].div_rem(self.rem);
+
+        self.rem = quot;
+        self.batch_pos += 1;
+
+        Some(rem as usize)
+    }
+}
+
+impl<I: Iterator<Item = usize>> Drop for Digits<'_, I> {
+    fn drop(&mut self) {
+        self.rem.zeroize();
+    }
+}
+
+/// A divisor in the `1..=u32::MAX` range, with its precomputed reciprocal
+/// used to divide by it without the (variable-time) division instruction.
+#[derive(Clone, Copy)]
+struct Divisor {
+    div: u64,
+    /// `(2^64 - 1) / div`, which is computed from public values only.
+    reciprocal: u64,
+}
+
+impl Divisor {
+    #[inline]
+    fn new(div: usize) -> Self {
+        debug_assert!((1..=u32::MAX as usize).contains(&div));
+
+        let div = div as u64;
+
+        Self {
+            div,
+            reciprocal: u64::MAX / div,
+        }
+    }
+
+    /// Returns `(x / div, x % div)`, where `x / div` must fit in 32 bits.
+    #[inline]
+    fn div_rem(self, x: u64) -> (u64, u64) {
+        // Since `x < 2^32 * div`, `x * reciprocal / 2^64` is either `x / div`
+        // or `x / div - 1`, which is fixed below without branching.
+        let mut quot = ((u128::from(x) * u128::from(self.reciprocal)) >> 64) as u64;
+        let mut rem = x - quot * self.div;
+        let (rem_minus_div, borrow) = rem.overflowing_sub(self.div);
+        let no_borrow = black_box(u64::from(borrow).wrapping_sub(1));
+
+        quot += 1 & no_borrow;
+        rem = (rem_minus_div & no_borrow) | (rem & !no_borrow);
+
+        (quot, rem)
+    }
+}
+
+/// Returns `0xFF` if `a == b`, and `0` otherwise.
+#[inline]
+pub(crate) fn eq_mask(a: usize, b: usize) -> u8 {
+    let diff = a ^ b;
+    // The most significant bit of `diff | -diff` is set iff `diff != 0`.
+    let is_different = ((diff | diff.wrapping_neg()) >> (usize::BITS - 1)) as u8;
+
+    black_box(is_different.wrapping_sub(1))
+}
+
+/// Returns `0xFF` if `a < b`, and `0` otherwise.
+///
+/// `a` and `b` must both be smaller than `2^(usize::BITS - 1)`.
+#[inline]
+pub(crate) fn lt_mask(a: usize, b: usize) -> u8 {
+    // `a - b` wraps around (and thus has its most significant bit set) iff
+    // `a < b`.
+    let is_less = (a.wrapping_sub(b) >> (usize::BITS - 1)) as u8;
+
+    black_box(is_less.wrapping_neg())
+}
+
+/// Returns `a` if `mask` is `0xFF`, and `b` if `mask` is `0`.
+#[inline]
+pub(crate) fn select(mask: u8, a: u8, b: u8) -> u8 {
+    (a & mask) | (b & !mask)
+}
+
+/// Inserts `ch` at index `at` in `buf[..=len]`, shifting `buf[at..len]` to
+/// the right, without branching or accessing memory depending on `at` or
+/// `ch`.
+#[inline]
+pub(crate) fn insert(buf: &mut [u8], len: usize, at: usize, ch: u8) {
+    debug_assert!(at <= len && len < buf.len());
+
+    // Iterate backwards so that `buf[i - 1]` is read before being updated.
+    for i in (0..=len).rev() {
+        let previous = if i == 0 { 0 } else { buf[i - 1] };
+        let shifted = select(lt_mask(at, i), previous, buf[i]);
+
+        buf[i] = select(eq_mask(at, i), ch, shifted);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn div_rem() {
+        let bytes = [0xAB, 0xCD, 0xEF, 0x01, 0x23, 0x45, 0x67, 0x89, 0xFE];
+        let mut quotient = Quotient::from_be_bytes(&bytes);
+        let mut expected = u128::from_be_bytes({
+            let mut padded = [0; 16];
+            padded[16 - bytes.len()..].copy_from_slice(&bytes);
+            padded
+        });
+
+        for div in [1, 2, 3, 7, 10, 26, 94, 512, 7776, u32::MAX as usize] {
+            assert_eq!(quotient.div_rem(div) as u128, expected % div as u128);
+
+            expected /= div as u128;
+        }
+
+        assert_eq!(quotient.div_rem(1 << 20) as u128, expected);
+    }
+
+    #[test]
+    fn digits() {
+        let bytes = [0x5A; 64];
+        let divisors = [
+            94, 94, 94, 94, 94, 26, 26, 10, 1, 1, 2, 3, 65_537, 70_000, 7,
+        ];
+        let mut expected = Quotient::from_be_bytes(&bytes);
+        let mut quotient = Quotient::from_be_bytes(&bytes);
+        let mut digits = quotient.digits(divisors.into_iter());
+
+        for div in divisors {
+            assert_eq!(digits.next(), Some(expected.div_rem(div)));
+        }
+
+        assert_eq!(digits.next(), None);
+        drop(digits);
+        assert_eq!(quotient.limbs, expected.limbs);
+    }
+
+    #[test]
+    fn is_less_than() {
+        let quotient = Quotient::from_be_bytes(&[0x01, 0x00, 0x00, 0x00, 0x00]);
+
+        assert!(quotient.is_less_than(1 << 33));
+        assert!(!quotient.is_less_than(1 << 32));
+        assert!(!Quotient::from_be_bytes(&[1, 0, 0, 0, 0, 0, 0, 0, 0]).is_less_than(u64::MAX));
+        assert!(Quotient::from_be_bytes(&[0; 12]).is_less_than(1));
+        assert!(!Quotient::from_be_bytes(&[0; 12]).is_less_than(0));
+    }
+
+    #[test]
+    fn masks() {
+        assert_eq!(eq_mask(3, 3), 0xFF);
+        assert_eq!(eq_mask(3, 4), 0);
+        assert_eq!(lt_mask(3, 4), 0xFF);
+        assert_eq!(lt_mask(4, 4), 0);
+        assert_eq!(lt_mask(5, 4), 0);
+        assert_eq!(select(0xFF, 1, 2), 1);
+        assert_eq!(select(0, 1, 2), 2);
+    }
+
+    #[test]
+    fn insert_at() {
+        for at in 0..=4 {
+            let mut buf = *b"abcd\0";
+            let mut expected = [0; 5];
+
+            expected[..at].copy_from_slice(&b"abcd"[..at]);
+            expected[at] = b'X';
+            expected[at + 1..].copy_from_slice(&b"abcd"[at..]);
+
+            insert(&mut buf, 4, at, b'X');
+
+            assert_eq!(buf, expected);
+        }
+    }
+}
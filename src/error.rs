@@ -0,0 +1,212 @@
+use core::fmt;
+
+/// An error returned by the fallible (`try_*`) functions of this crate when
+/// one of their inputs is invalid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The master password is empty.
+    EmptyMasterPassword,
+
+    /// The salt is empty.
+    EmptySalt,
+
+    /// The salt is longer than what can be computed without allocating.
+    SaltTooLong { max: usize, got: usize },
+
+    /// The salt is shorter than what the key derivation function supports.
+    SaltTooShort { min: usize, got: usize },
+
+    /// The number of iterations is zero.
+    ZeroIterations,
+
+    /// The parameters of the key derivation function are invalid.
+    InvalidKdfParams,
+
+    /// The algorithm cannot be used for this operation, e.g. a memory-hard
+    /// function given to [`MasterKey`](crate::MasterKey), which only supports
+    /// PBKDF2.
+    UnsupportedAlgorithm,
+
+    /// The requested entropy length is not in the `min..=max` range.
+    EntropyLengthOutOfRange { min: usize, max: usize, got: usize },
+
+    /// The entropy used to render a password is empty.
+    EmptyEntropy,
+
+    /// The entropy used to render a password is longer than `max` bytes.
+    EntropyTooLong { max: usize, got: usize },
+
+    /// The character set used to render a password is empty.
+    EmptyCharset,
+
+    /// An alphabet has more than `max` classes.
+    TooManyClasses { max: usize, got: usize },
+
+    /// The class at the given index of an alphabet is empty, possibly after
+    /// excluding ambiguous characters.
+    EmptyClass { index: usize },
+
+    /// The class at the given index of an alphabet contains a character that
+    /// is not printable ASCII.
+    InvalidClassCharacter { index: usize },
+
+    /// The class at the given index of an alphabet contains a character that
+    /// appears earlier in the alphabet.
+    DuplicateCharacter { index: usize },
+
+    /// The requested password length is not in the `min..=max` range.
+    LengthOutOfRange { min: usize, max: usize, got: usize },
+
+    /// The counter is greater than `max`, e.g. in a salt generated with
+    /// [`try_generate_strict_salt_to`](crate::try_generate_strict_salt_to) or
+    /// by a [`Profile`](crate::Profile).
+    CounterOutOfRange { max: u64, got: u64 },
+
+    /// The output buffer is smaller than the `required` size.
+    BufferTooSmall { required: usize },
+
+    /// The number of output buffers does not match the number of inputs.
+    OutputCountMismatch { expected: usize, got: usize },
+
+    /// The requested number of words of a passphrase is not in the `min..=max`
+    /// range.
+    WordCountOutOfRange { min: usize, max: usize, got: usize },
+
+    /// The wordlist used to render a passphrase is empty.
+    EmptyWordlist,
+
+    /// The word at the given index of a wordlist is empty.
+    EmptyWord { index: usize },
+
+    /// The symbols used to render a passphrase are not all printable ASCII.
+    InvalidSymbols,
+
+    /// The entropy was exhausted before a PIN that is not weak was found, or
+    /// is too short to render a passphrase (see
+    /// [`passphrase_entropy_len`](crate::passphrase_entropy_len)).
+    EntropyExhausted,
+
+    /// The operation was cancelled by its caller, e.g. from the progress
+    /// callback of
+    /// [`try_generate_entropy_with_progress_to`](crate::try_generate_entropy_with_progress_to).
+    Cancelled,
+
+    /// None of the passwords generated with the `attempts` counters that were
+    /// tried satisfies the [`PasswordPolicy`](crate::PasswordPolicy).
+    PolicyNotSatisfied { attempts: u32 },
+
+    /// The [`PasswordRules`](crate::PasswordRules) are malformed at the given
+    /// byte offset.
+    InvalidPasswordRules { position: usize },
+
+    /// The website is not a valid (internationalized) domain name.
+    InvalidDomain,
+
+    /// The login is empty, which LessPass v1 does not support.
+    EmptyLogin,
+
+    /// The template used to render a LessPass v1 password is empty, or
+    /// contains a character that is not a class of LessPass v1.
+    InvalidTemplate,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Error::EmptyMasterPassword => f.write_str("the master password is empty"),
+            Error::EmptySalt => f.write_str("the salt is empty"),
+            Error::SaltTooLong { max, got } => {
+                write!(
+                    f,
+                    "the salt is {got} bytes long, but at most {max} bytes are supported"
+                )
+            }
+            Error::SaltTooShort { min, got } => write!(
+                f,
+                "the salt is {got} bytes long, but at least {min} bytes are required"
+            ),
+            Error::ZeroIterations => f.write_str("the number of iterations must be at least 1"),
+            Error::InvalidKdfParams => {
+                f.write_str("the parameters of the key derivation function are invalid")
+            }
+            Error::UnsupportedAlgorithm => {
+                f.write_str("the algorithm is not supported by this operation")
+            }
+            Error::EntropyLengthOutOfRange { min, max, got } => write!(
+                f,
+                "the entropy length must be in the [{min}; {max}] range, but is {got}"
+            ),
+            Error::EmptyEntropy => f.write_str("the entropy is empty"),
+            Error::EntropyTooLong { max, got } => write!(
+                f,
+                "the entropy is {got} bytes long, but at most {max} bytes are supported"
+            ),
+            Error::EmptyCharset => f.write_str("the character set is empty"),
+            Error::TooManyClasses { max, got } => write!(
+                f,
+                "the alphabet has {got} classes, but at most {max} are supported"
+            ),
+            Error::EmptyClass { index } => write!(f, "class {index} of the alphabet is empty"),
+            Error::InvalidClassCharacter { index } => write!(
+                f,
+                "class {index} of the alphabet contains a character that is not printable ASCII"
+            ),
+            Error::DuplicateCharacter { index } => write!(
+                f,
+                "class {index} of the alphabet contains a character that appears earlier in the alphabet"
+            ),
+            Error::LengthOutOfRange { min, max, got } => write!(
+                f,
+                "the password length must be in the [{min}; {max}] range, but is {got}"
+            ),
+            Error::CounterOutOfRange { max, got } => {
+                write!(f, "the counter must be at most {max}, but is {got}")
+            }
+            Error::BufferTooSmall { required } => {
+                write!(
+                    f,
+                    "the output buffer must be at least {required} bytes long"
+                )
+            }
+            Error::OutputCountMismatch { expected, got } => write!(
+                f,
+                "{expected} output buffers are required, but {got} were given"
+            ),
+            Error::WordCountOutOfRange { min, max, got } => write!(
+                f,
+                "the number of words must be in the [{min}; {max}] range, but is {got}"
+            ),
+            Error::EmptyWordlist => f.write_str("the wordlist is empty"),
+            Error::EmptyWord { index } => write!(f, "word {index} of the wordlist is empty"),
+            Error::InvalidSymbols => f.write_str("the symbols are not all printable ASCII"),
+            Error::EntropyExhausted => {
+                f.write_str("the entropy was exhausted before the rendering was complete")
+            }
+            Error::Cancelled => f.write_str("the operation was cancelled"),
+            Error::PolicyNotSatisfied { attempts } => write!(
+                f,
+                "none of the {attempts} generated passwords satisfies the policy"
+            ),
+            Error::InvalidPasswordRules { position } => {
+                write!(f, "the password rules are invalid at byte {position}")
+            }
+            Error::InvalidDomain => f.write_str("the website is not a valid domain name"),
+            Error::EmptyLogin => f.write_str("the login is empty"),
+            Error::InvalidTemplate => f.write_str("the template is invalid"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// Unwraps the result of a `try_*` function in its panicking counterpart.
+#[track_caller]
+#[inline]
+pub(crate) fn unwrap<T>(result: Result<T, Error>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(err) => panic!("{}", err),
+    }
+}
@@ -0,0 +1,286 @@
+//! Generation of passwords longer than [`MAX_PASSWORD_LEN`].
+//!
+//! The canonical LessPass algorithm renders at most [`MAX_PASSWORD_LEN`]
+//! characters since the entropy it uses (32 bytes for SHA-256) runs out of
+//! randomness for longer passwords. In extended mode, PBKDF2 is asked for as
+//! many bytes of entropy as needed (see [`extended_entropy_len`]), and the
+//! password is rendered using the same algorithm over an arbitrarily large
+//! number.
+//!
+//! Rendering a password from the same entropy gives the same result in both
+//! modes, but since extended mode usually generates more entropy, passwords
+//! generated in extended mode are not compatible with other LessPass
+//! implementations.
+
+use core::mem::MaybeUninit;
+
+use crate::ct::Quotient;
+use crate::{
+    check_render_args, derive_entropy, error, render_with, slice_to_maybe_uninit_mut, Alphabet,
+    Error, Kdf,
+};
+
+#[cfg(doc)]
+use crate::{generate_entropy_to, render_password_to, CharacterSet, Entropy, MAX_PASSWORD_LEN};
+
+/// The maximum length of the rendered password in extended mode, inclusive.
+pub const MAX_EXTENDED_PASSWORD_LEN: usize = 512;
+
+/// The maximum length of the entropy in bytes in extended mode, inclusive.
+pub const MAX_EXTENDED_ENTROPY_LEN: usize = 512;
+
+/// Returns the number of bytes of entropy needed to render a password of
+/// length `len` using the characters in `charset` without running out of
+/// randomness.
+///
+/// If `charset` is a [`CharacterSet`] and `len` is at most
+/// [`MAX_EXTENDED_PASSWORD_LEN`], the result always lies in the
+/// `0..=MAX_EXTENDED_ENTROPY_LEN` range. Custom [`Alphabet`]s with many
+/// classes may need more entropy than that, in which case
+/// [`Entropy::generate_extended`] returns
+/// [`Error::EntropyLengthOutOfRange`].
+pub fn extended_entropy_len<'a>(charset: impl Into<Alphabet<'a>>, len: usize) -> usize {
+    let alphabet = charset.into();
+    let initial_len = len.saturating_sub(alphabet.classes().len());
+
+    // Characters picked from the whole alphabet.
+    let mut bits = initial_len * ceil_log2(alphabet.len());
+
+    for i in 0..alphabet.classes().len() {
+        // Character picked from each class, and its position in the password.
+        bits += ceil_log2(alphabet.class_len(i)) + ceil_log2(initial_len + i);
+    }
+
+    entropy_len_for_bits(bits)
+}
+
+/// Returns the number of bytes of entropy needed to consume `bits` bits of it
+/// by successive divisions.
+pub(crate) const fn entropy_len_for_bits(bits: usize) -> usize {
+    // Add 8 bytes of margin so that the last divisions are not biased towards
+    // small remainders.
+    (bits + 7) / 8 + 8
+}
+
+/// Returns the number of bits needed to represent any number in `0..x`.
+pub(crate) const fn ceil_log2(x: usize) -> usize {
+    if x <= 1 {
+        0
+    } else {
+        (usize::BITS - (x - 1).leading_zeros()) as usize
+    }
+}
+
+/// Same as [`generate_entropy_to`], but allows `output` to be up to
+/// [`MAX_EXTENDED_ENTROPY_LEN`] bytes long.
+///
+/// # Panics
+///
+/// Panics in the same cases as [`generate_entropy_to`], with
+/// [`MAX_EXTENDED_ENTROPY_LEN`] as maximum length.
+#[track_caller]
+#[inline]
+pub fn generate_extended_entropy_to(
+    master_password: &str,
+    salt: &[u8],
+    algorithm: impl Kdf,
+    iterations: u32,
+    output: &mut [u8],
+) {
+    error::unwrap(try_generate_extended_entropy_to(
+        master_password,
+        salt,
+        algorithm,
+        iterations,
+        output,
+    ))
+}
+
+/// Same as [`generate_extended_entropy_to`], but returns an [`Error`] instead
+/// of panicking if its inputs are invalid.
+#[inline]
+pub fn try_generate_extended_entropy_to(
+    master_password: &str,
+    salt: &[u8],
+    algorithm: impl Kdf,
+    iterations: u32,
+    output: &mut [u8],
+) -> Result<(), Error> {
+    derive_entropy(
+        master_password,
+        salt,
+        &algorithm,
+        iterations,
+        output,
+        MAX_EXTENDED_ENTROPY_LEN,
+    )
+}
+
+/// Same as [`try_render_extended_password_to`], but works with an
+/// uninitialized output buffer, which is okay since it only writes to it,
+/// without reading from it.
+pub fn try_render_extended_password_to_uninit<'a, 'out>(
+    entropy: &[u8],
+    charset: impl Into<Alphabet<'a>>,
+    output: &'out mut [MaybeUninit<u8>],
+) -> Result<&'out mut [u8], Error> {
+    let alphabet = charset.into();
+
+    check_render_args(
+        entropy,
+        &alphabet,
+        output.len(),
+        MAX_EXTENDED_ENTROPY_LEN,
+        MAX_EXTENDED_PASSWORD_LEN,
+    )?;
+
+    let mut quotient = Quotient::from_be_bytes(entropy);
+
+    Ok(render_with(&alphabet, output, &mut quotient))
+}
+
+/// Same as [`render_password_to`], but allows `output` to be up to
+/// [`MAX_EXTENDED_PASSWORD_LEN`] bytes long and `entropy` to be up to
+/// [`MAX_EXTENDED_ENTROPY_LEN`] bytes long.
+///
+/// For entropy of at most [`MAX_ENTROPY_LEN`](crate::MAX_ENTROPY_LEN) bytes
+/// and passwords of at most [`MAX_PASSWORD_LEN`] characters, this function
+/// returns the same result as [`render_password_to`].
+///
+/// # Panics
+///
+/// Panics in the same cases as [`render_password_to`], with the extended
+/// maximum lengths.
+#[track_caller]
+#[inline]
+pub fn render_extended_password_to<'a>(
+    entropy: &[u8],
+    charset: impl Into<Alphabet<'a>>,
+    output: &mut [u8],
+) {
+    error::unwrap(try_render_extended_password_to(entropy, charset, output))
+}
+
+/// Same as [`render_extended_password_to`], but returns an [`Error`] instead
+/// of panicking if its inputs are invalid.
+#[inline]
+pub fn try_render_extended_password_to<'a>(
+    entropy: &[u8],
+    charset: impl Into<Alphabet<'a>>,
+    output: &mut [u8],
+) -> Result<(), Error> {
+    try_render_extended_password_to_uninit(entropy, charset, slice_to_maybe_uninit_mut(output))
+        .map(|_| ())
+}
+
+/// Same as [`render_extended_password_to`], but directly returns the rendered
+/// password instead of requiring a mutable output buffer.
+#[cfg(feature = "std")]
+#[track_caller]
+#[inline]
+pub fn render_extended_password<'a>(
+    entropy: &[u8],
+    charset: impl Into<Alphabet<'a>>,
+    len: usize,
+) -> std::string::String {
+    error::unwrap(try_render_extended_password(entropy, charset, len))
+}
+
+/// Same as [`render_extended_password`], but returns an [`Error`] instead of
+/// panicking if its inputs are invalid.
+#[cfg(feature = "std")]
+pub fn try_render_extended_password<'a>(
+    entropy: &[u8],
+    charset: impl Into<Alphabet<'a>>,
+    len: usize,
+) -> Result<std::string::String, Error> {
+    let alphabet = charset.into();
+
+    // Validate arguments before allocating the output buffer.
+    check_render_args(
+        entropy,
+        &alphabet,
+        len,
+        MAX_EXTENDED_ENTROPY_LEN,
+        MAX_EXTENDED_PASSWORD_LEN,
+    )?;
+
+    let mut uninit_output = crate::uninit_vec(len);
+
+    try_render_extended_password_to_uninit(entropy, alphabet, &mut uninit_output)?;
+
+    // SAFETY: `uninit_output` was fully initialized in
+    // `try_render_extended_password_to_uninit`.
+    let output = unsafe { crate::assume_init_vec(uninit_output) };
+
+    // SAFETY: characters are all extracted from `alphabet`, which only contains
+    // ASCII characters.
+    Ok(unsafe { std::string::String::from_utf8_unchecked(output) })
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::{
+        generate_entropy, generate_salt, render_password, Algorithm, CharacterSet, MAX_ENTROPY_LEN,
+        MAX_PASSWORD_LEN,
+    };
+
+    #[test]
+    fn entropy_len() {
+        assert_eq!(extended_entropy_len(CharacterSet::All, 16), 23);
+        assert_eq!(extended_entropy_len(CharacterSet::Digits, 64), 41);
+        assert!(
+            extended_entropy_len(CharacterSet::All, MAX_EXTENDED_PASSWORD_LEN)
+                <= MAX_EXTENDED_ENTROPY_LEN
+        );
+    }
+
+    #[test]
+    fn same_as_default_mode() {
+        let salt = generate_salt("example.org", "contact@example.org", 1);
+        let entropy = generate_entropy("password", &salt, Algorithm::SHA512, 1_000);
+
+        for len in [5, 16, MAX_PASSWORD_LEN] {
+            for charset in [
+                CharacterSet::All,
+                CharacterSet::Digits,
+                CharacterSet::Letters,
+            ] {
+                assert_eq!(
+                    render_extended_password(&entropy, charset, len),
+                    render_password(&entropy, charset, len),
+                );
+            }
+        }
+
+        assert_eq!(entropy.len(), MAX_ENTROPY_LEN);
+    }
+
+    #[test]
+    fn long_password() {
+        let charset = CharacterSet::Lowercase | CharacterSet::Digits;
+        let salt = generate_salt("example.org", "contact@example.org", 1);
+        let mut entropy = [0; MAX_EXTENDED_ENTROPY_LEN];
+        let entropy = &mut entropy[..extended_entropy_len(charset, 64)];
+
+        generate_extended_entropy_to("password", &salt, Algorithm::SHA256, 1_000, entropy);
+
+        let password = render_extended_password(entropy, charset, 64);
+
+        assert_eq!(password.len(), 64);
+        assert!(password.bytes().any(|c| c.is_ascii_lowercase()));
+        assert!(password.bytes().any(|c| c.is_ascii_digit()));
+        assert!(password
+            .bytes()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit()));
+        assert_eq!(
+            try_render_extended_password(entropy, charset, MAX_EXTENDED_PASSWORD_LEN + 1),
+            Err(Error::LengthOutOfRange {
+                min: 5,
+                max: MAX_EXTENDED_PASSWORD_LEN,
+                got: MAX_EXTENDED_PASSWORD_LEN + 1
+            }),
+        );
+    }
+}
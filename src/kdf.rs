@@ -0,0 +1,392 @@
+//! Key derivation functions used to generate entropy from a master password
+//! and a salt.
+//!
+//! Besides PBKDF2 with SHA-2 (the canonical LessPass algorithm), memory-hard
+//! functions may be used when the corresponding features are enabled.
+//!
+//! Note: Using these memory-hard functions makes the generated passwords
+//! different from every other LessPass implementation.
+
+use pbkdf2::pbkdf2_hmac;
+use sha2::{Sha256, Sha384, Sha512};
+
+use crate::{get_fingerprint, Algorithm, Error};
+
+/// A function that derives entropy from a master password and a salt.
+///
+/// This trait is implemented by [`Algorithm`], and may be implemented to use
+/// other functions (e.g. PBKDF2 with another hash function, or an HMAC
+/// computed by a hardware token) with [`generate_entropy_to`],
+/// [`Entropy::generate`] or [`Profile::with_kdf`]:
+///
+/// ```
+/// use lesspass::{Error, Kdf};
+///
+/// /// A (very insecure) function that repeats the salt.
+/// struct RepeatSalt;
+///
+/// impl Kdf for RepeatSalt {
+///     fn entropy_len(&self) -> usize {
+///         32
+///     }
+///
+///     fn derive(
+///         &self,
+///         _master_password: &str,
+///         salt: &[u8],
+///         _iterations: u32,
+///         output: &mut [u8],
+///     ) -> Result<(), Error> {
+///         for (out, byte) in output.iter_mut().zip(salt.iter().cycle()) {
+///             *out = *byte;
+///         }
+///
+///         Ok(())
+///     }
+/// }
+///
+/// let mut entropy = [0; 32];
+///
+/// lesspass::generate_entropy_to("password", b"salt", RepeatSalt, 1, &mut entropy);
+///
+/// assert_eq!(&entropy[..8], b"saltsalt");
+/// ```
+///
+/// [`generate_entropy_to`]: crate::generate_entropy_to
+/// [`Entropy::generate`]: crate::Entropy::generate
+/// [`Profile::with_kdf`]: crate::Profile::with_kdf
+pub trait Kdf {
+    /// Returns the length in bytes of the entropy generated by
+    /// [`generate_entropy`](crate::generate_entropy) with this function.
+    fn entropy_len(&self) -> usize;
+
+    /// Derives `output.len()` bytes of entropy from the given master password
+    /// and salt, running `iterations` iterations if the function supports it.
+    ///
+    /// The master password and salt are never empty, and `output` is never
+    /// empty or longer than
+    /// [`MAX_EXTENDED_ENTROPY_LEN`](crate::MAX_EXTENDED_ENTROPY_LEN) bytes.
+    fn derive(
+        &self,
+        master_password: &str,
+        salt: &[u8],
+        iterations: u32,
+        output: &mut [u8],
+    ) -> Result<(), Error>;
+
+    /// Returns the fingerprint of the given master password, which is
+    /// [`get_fingerprint`] by default.
+    fn fingerprint(&self, master_password: &str) -> [u8; 32] {
+        get_fingerprint(master_password)
+    }
+
+    /// Returns the built-in [`Algorithm`] that this function is equivalent
+    /// to, if any, which is `None` by default.
+    ///
+    /// This allows functions such as [`generate_batch`] to use faster
+    /// implementations of built-in algorithms.
+    ///
+    /// [`generate_batch`]: crate::generate_batch
+    fn as_algorithm(&self) -> Option<Algorithm> {
+        None
+    }
+}
+
+impl<K: Kdf + ?Sized> Kdf for &K {
+    #[inline]
+    fn entropy_len(&self) -> usize {
+        (**self).entropy_len()
+    }
+
+    #[inline]
+    fn derive(
+        &self,
+        master_password: &str,
+        salt: &[u8],
+        iterations: u32,
+        output: &mut [u8],
+    ) -> Result<(), Error> {
+        (**self).derive(master_password, salt, iterations, output)
+    }
+
+    #[inline]
+    fn fingerprint(&self, master_password: &str) -> [u8; 32] {
+        (**self).fingerprint(master_password)
+    }
+
+    #[inline]
+    fn as_algorithm(&self) -> Option<Algorithm> {
+        (**self).as_algorithm()
+    }
+}
+
+impl Kdf for Algorithm {
+    #[inline]
+    fn entropy_len(&self) -> usize {
+        Algorithm::entropy_len(*self)
+    }
+
+    fn derive(
+        &self,
+        master_password: &str,
+        salt: &[u8],
+        iterations: u32,
+        output: &mut [u8],
+    ) -> Result<(), Error> {
+        if iterations == 0 && self.is_pbkdf2() {
+            return Err(Error::ZeroIterations);
+        }
+
+        let password = master_password.as_bytes();
+
+        match *self {
+            Algorithm::SHA256 => pbkdf2_hmac::<Sha256>(password, salt, iterations, output),
+            Algorithm::SHA384 => pbkdf2_hmac::<Sha384>(password, salt, iterations, output),
+            Algorithm::SHA512 => pbkdf2_hmac::<Sha512>(password, salt, iterations, output),
+            #[cfg(feature = "argon2")]
+            Algorithm::Argon2id(params) => params.derive(master_password, salt, output)?,
+            #[cfg(feature = "scrypt")]
+            Algorithm::Scrypt(params) => params.derive(master_password, salt, output)?,
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    fn as_algorithm(&self) -> Option<Algorithm> {
+        Some(*self)
+    }
+}
+
+/// The parameters of Argon2id, used by [`Algorithm::Argon2id`].
+#[cfg(feature = "argon2")]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Argon2Params {
+    memory: u32,
+    time: u32,
+    parallelism: u32,
+}
+
+#[cfg(feature = "argon2")]
+impl Argon2Params {
+    /// The memory size used by default, in KiB.
+    pub const DEFAULT_MEMORY: u32 = argon2::Params::DEFAULT_M_COST;
+
+    /// The number of passes over the memory used by default.
+    pub const DEFAULT_TIME: u32 = argon2::Params::DEFAULT_T_COST;
+
+    /// The degree of parallelism used by default.
+    pub const DEFAULT_PARALLELISM: u32 = argon2::Params::DEFAULT_P_COST;
+
+    /// Creates new Argon2id parameters, with the memory size given in KiB.
+    ///
+    /// Parameters are validated when deriving entropy, which fails with
+    /// [`Error::InvalidKdfParams`] if they are not supported by Argon2id.
+    pub const fn new(memory: u32, time: u32, parallelism: u32) -> Self {
+        Self {
+            memory,
+            time,
+            parallelism,
+        }
+    }
+
+    /// Returns the memory size, in KiB.
+    pub const fn memory(&self) -> u32 {
+        self.memory
+    }
+
+    /// Returns the number of passes over the memory.
+    pub const fn time(&self) -> u32 {
+        self.time
+    }
+
+    /// Returns the degree of parallelism.
+    pub const fn parallelism(&self) -> u32 {
+        self.parallelism
+    }
+
+    /// Derives entropy from the master password and salt using Argon2id.
+    pub(crate) fn derive(
+        &self,
+        master_password: &str,
+        salt: &[u8],
+        output: &mut [u8],
+    ) -> Result<(), Error> {
+        let params = argon2::Params::new(self.memory, self.time, self.parallelism, None)
+            .map_err(|_| Error::InvalidKdfParams)?;
+        let argon2 =
+            argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params);
+
+        argon2
+            .hash_password_into(master_password.as_bytes(), salt, output)
+            .map_err(|err| match err {
+                argon2::Error::SaltTooShort => Error::SaltTooShort {
+                    min: argon2::MIN_SALT_LEN,
+                    got: salt.len(),
+                },
+                _ => Error::InvalidKdfParams,
+            })
+    }
+}
+
+#[cfg(feature = "argon2")]
+impl Default for Argon2Params {
+    #[inline]
+    fn default() -> Self {
+        Self::new(
+            Self::DEFAULT_MEMORY,
+            Self::DEFAULT_TIME,
+            Self::DEFAULT_PARALLELISM,
+        )
+    }
+}
+
+/// The parameters of scrypt, used by [`Algorithm::Scrypt`].
+#[cfg(feature = "scrypt")]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ScryptParams {
+    log_n: u8,
+    r: u32,
+    p: u32,
+}
+
+#[cfg(feature = "scrypt")]
+impl ScryptParams {
+    /// The base-2 logarithm of the cost parameter `N` used by default.
+    pub const DEFAULT_LOG_N: u8 = scrypt::Params::RECOMMENDED_LOG_N;
+
+    /// The block size parameter `r` used by default.
+    pub const DEFAULT_R: u32 = scrypt::Params::RECOMMENDED_R;
+
+    /// The parallelization parameter `p` used by default.
+    pub const DEFAULT_P: u32 = scrypt::Params::RECOMMENDED_P;
+
+    /// Creates new scrypt parameters, with the cost parameter `N` given as
+    /// its base-2 logarithm.
+    ///
+    /// Parameters are validated when deriving entropy, which fails with
+    /// [`Error::InvalidKdfParams`] if they are not supported by scrypt.
+    pub const fn new(log_n: u8, r: u32, p: u32) -> Self {
+        Self { log_n, r, p }
+    }
+
+    /// Returns the base-2 logarithm of the cost parameter `N`.
+    pub const fn log_n(&self) -> u8 {
+        self.log_n
+    }
+
+    /// Returns the block size parameter `r`.
+    pub const fn r(&self) -> u32 {
+        self.r
+    }
+
+    /// Returns the parallelization parameter `p`.
+    pub const fn p(&self) -> u32 {
+        self.p
+    }
+
+    /// Derives entropy from the master password and salt using scrypt.
+    pub(crate) fn derive(
+        &self,
+        master_password: &str,
+        salt: &[u8],
+        output: &mut [u8],
+    ) -> Result<(), Error> {
+        // The length given to `Params::new` is only used to encode password
+        // hashes, and is ignored by `scrypt::scrypt`.
+        let params =
+            scrypt::Params::new(self.log_n, self.r, self.p, scrypt::Params::RECOMMENDED_LEN)
+                .map_err(|_| Error::InvalidKdfParams)?;
+
+        scrypt::scrypt(master_password.as_bytes(), salt, &params, output)
+            .map_err(|_| Error::InvalidKdfParams)
+    }
+}
+
+#[cfg(feature = "scrypt")]
+impl Default for ScryptParams {
+    #[inline]
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_LOG_N, Self::DEFAULT_R, Self::DEFAULT_P)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::{generate_entropy, generate_salt, try_generate_entropy};
+
+    #[test]
+    fn dyn_kdf() {
+        let salt = generate_salt("example.org", "contact@example.org", 1);
+
+        for algorithm in [Algorithm::SHA256, Algorithm::SHA384, Algorithm::SHA512] {
+            let kdf: &dyn Kdf = &algorithm;
+
+            assert_eq!(
+                generate_entropy("password", &salt, kdf, 1_000),
+                generate_entropy("password", &salt, algorithm, 1_000),
+            );
+        }
+
+        assert_eq!(
+            try_generate_entropy("password", &salt, &Algorithm::SHA256 as &dyn Kdf, 0),
+            Err(Error::ZeroIterations),
+        );
+    }
+}
+
+#[cfg(all(test, feature = "std", feature = "argon2", feature = "scrypt"))]
+mod memory_hard_tests {
+    use super::*;
+    use crate::{generate_entropy, generate_salt, try_generate_entropy, Algorithm};
+
+    const ARGON2: Algorithm = Algorithm::Argon2id(Argon2Params::new(64, 1, 1));
+    const SCRYPT: Algorithm = Algorithm::Scrypt(ScryptParams::new(4, 1, 1));
+
+    #[test]
+    fn generate() {
+        let salt = generate_salt("example.org", "contact@example.org", 1);
+        let pbkdf2 = generate_entropy("password", &salt, Algorithm::SHA256, 1);
+        let argon2 = generate_entropy("password", &salt, ARGON2, 1);
+        let scrypt = generate_entropy("password", &salt, SCRYPT, 1);
+
+        assert_eq!(argon2.len(), 32);
+        assert_eq!(scrypt.len(), 32);
+        assert_ne!(argon2, pbkdf2);
+        assert_ne!(scrypt, pbkdf2);
+        assert_ne!(argon2, scrypt);
+
+        // Iterations are ignored.
+        assert_eq!(generate_entropy("password", &salt, ARGON2, 2), argon2);
+        assert_eq!(generate_entropy("password", &salt, SCRYPT, 2), scrypt);
+    }
+
+    #[test]
+    fn invalid() {
+        let salt = generate_salt("example.org", "contact@example.org", 1);
+
+        assert_eq!(
+            try_generate_entropy("password", b"a1", ARGON2, 1),
+            Err(Error::SaltTooShort { min: 8, got: 2 }),
+        );
+        assert_eq!(
+            try_generate_entropy(
+                "password",
+                &salt,
+                Algorithm::Argon2id(Argon2Params::new(64, 0, 1)),
+                1
+            ),
+            Err(Error::InvalidKdfParams),
+        );
+        assert_eq!(
+            try_generate_entropy(
+                "password",
+                &salt,
+                Algorithm::Scrypt(ScryptParams::new(4, 0, 1)),
+                1
+            ),
+            Err(Error::InvalidKdfParams),
+        );
+    }
+}
@@ -0,0 +1,655 @@
+//! PBKDF2-HMAC-SHA256 computed for several salts at once.
+//!
+//! Each iteration of PBKDF2 depends on the previous one, so a single
+//! derivation cannot be parallelized. However the derivations of several
+//! salts (or of several blocks of extended entropy) are independent: here,
+//! they are computed in lock-step, with one SIMD lane per derivation. Each
+//! iteration of PBKDF2-HMAC-SHA256 (after the first one) hashes a single
+//! block for the inner hash and a single block for the outer hash, so both
+//! the HMAC key schedule and the padding of these blocks are computed once.
+//!
+//! On x86-64, 8 lanes are used if AVX2 is available, and 4 lanes (with SSE2)
+//! otherwise. Other architectures use a portable implementation of 4 lanes.
+
+use pbkdf2::hmac::digest::{Digest, FixedOutput, Update};
+use pbkdf2::hmac::Hmac;
+use sha2::Sha256;
+use zeroize::Zeroize;
+
+/// The maximum number of lanes processed at once.
+pub(crate) const MAX_LANES: usize = 8;
+
+/// The length of the output of SHA-256, in bytes.
+const OUTPUT_LEN: usize = 32;
+
+/// The initial state of SHA-256.
+const IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// The round constants of SHA-256.
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// The states of HMAC-SHA256 after hashing the inner and outer padded keys.
+pub(crate) struct HmacStates {
+    inner: [u32; 8],
+    outer: [u32; 8],
+}
+
+impl HmacStates {
+    /// Computes the HMAC-SHA256 states of the given key.
+    pub(crate) fn new(key: &[u8]) -> Self {
+        let mut padded_key = [0; 64];
+
+        if key.len() > padded_key.len() {
+            let mut digest = Sha256::digest(key);
+
+            padded_key[..OUTPUT_LEN].copy_from_slice(&digest);
+            digest.as_mut_slice().zeroize();
+        } else {
+            padded_key[..key.len()].copy_from_slice(key);
+        }
+
+        let compress_padded_key = |pad: u8| {
+            let mut block = [0; 16];
+
+            for (word, bytes) in block.iter_mut().zip(padded_key.chunks_exact(4)) {
+                *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+                    ^ u32::from_be_bytes([pad; 4]);
+            }
+
+            let mut state = IV.map(|word| [word]);
+
+            compress(&mut state, &block.map(|word| [word]));
+            block.zeroize();
+
+            state.map(|[word]| word)
+        };
+        let states = Self {
+            inner: compress_padded_key(0x36),
+            outer: compress_padded_key(0x5c),
+        };
+
+        padded_key.zeroize();
+
+        states
+    }
+}
+
+impl Drop for HmacStates {
+    fn drop(&mut self) {
+        self.inner.zeroize();
+        self.outer.zeroize();
+    }
+}
+
+/// Writes PBKDF2-HMAC-SHA256 of each salt to the corresponding output, using
+/// `mac` (keyed with the password) for the first iteration and `states`
+/// (computed from the password) for the following ones.
+///
+/// Salts and outputs must have the same length, and outputs must not be
+/// empty.
+pub(crate) fn pbkdf2<S: AsRef<[u8]>, O: AsMut<[u8]>>(
+    mac: &Hmac<Sha256>,
+    states: &HmacStates,
+    salts: &[S],
+    iterations: u32,
+    outputs: &mut [O],
+) {
+    debug_assert_eq!(salts.len(), outputs.len());
+
+    let lanes = lanes();
+    let mut blocks = salts.iter().zip(outputs).flat_map(|(salt, output)| {
+        output
+            .as_mut()
+            .chunks_mut(OUTPUT_LEN)
+            .enumerate()
+            .map(move |(i, block)| (salt.as_ref(), i as u32 + 1, block))
+    });
+
+    loop {
+        let mut first = [[0; OUTPUT_LEN]; MAX_LANES];
+        let mut result = [[0; OUTPUT_LEN]; MAX_LANES];
+        let mut outputs: [Option<&mut [u8]>; MAX_LANES] = Default::default();
+        let mut len = 0;
+
+        for (salt, block_index, output) in blocks.by_ref().take(lanes) {
+            // First iteration: `U_1 = HMAC(password, salt || block_index)`.
+            let mut mac = mac.clone();
+
+            mac.update(salt);
+            mac.update(&block_index.to_be_bytes());
+            mac.finalize_into((&mut first[len]).into());
+
+            outputs[len] = Some(output);
+            len += 1;
+        }
+
+        if len == 0 {
+            break;
+        }
+
+        iterate(states, iterations, &first[..len], &mut result[..len]);
+
+        for (output, result) in outputs.iter_mut().zip(&result).take(len) {
+            if let Some(output) = output {
+                output.copy_from_slice(&result[..output.len()]);
+            }
+        }
+
+        first.zeroize();
+        result.zeroize();
+    }
+}
+
+/// Returns the number of lanes processed at once.
+#[inline]
+fn lanes() -> usize {
+    if has_avx2() {
+        8
+    } else {
+        4
+    }
+}
+
+/// Returns whether AVX2 is available.
+#[inline]
+fn has_avx2() -> bool {
+    #[cfg(all(target_arch = "x86_64", feature = "std"))]
+    {
+        std::is_x86_feature_detected!("avx2")
+    }
+    #[cfg(not(all(target_arch = "x86_64", feature = "std")))]
+    {
+        cfg!(all(target_arch = "x86_64", target_feature = "avx2"))
+    }
+}
+
+/// Computes the iterations of PBKDF2 that follow the first one: given
+/// `U_1` for each lane in `first`, writes `U_1 ^ U_2 ^ ... ^ U_iterations` to
+/// `result`.
+fn iterate(
+    states: &HmacStates,
+    iterations: u32,
+    first: &[[u8; OUTPUT_LEN]],
+    result: &mut [[u8; OUTPUT_LEN]],
+) {
+    debug_assert!(first.len() <= MAX_LANES && first.len() == result.len());
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        if has_avx2() {
+            // SAFETY: AVX2 is available.
+            return unsafe { x86_64::iterate_avx2(states, iterations, first, result) };
+        }
+
+        for (first, result) in first.chunks(4).zip(result.chunks_mut(4)) {
+            iterate_lanes::<x86_64::Sse2>(states, iterations, first, result);
+        }
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    for (first, result) in first.chunks(4).zip(result.chunks_mut(4)) {
+        iterate_lanes::<[u32; 4]>(states, iterations, first, result);
+    }
+}
+
+/// Same as [`iterate`], with one lane per element of `W`.
+#[inline(always)]
+fn iterate_lanes<W: Word>(
+    states: &HmacStates,
+    iterations: u32,
+    first: &[[u8; OUTPUT_LEN]],
+    result: &mut [[u8; OUTPUT_LEN]],
+) {
+    debug_assert!(first.len() <= W::LANES && first.len() == result.len());
+
+    // Transpose the inputs so that the `i`-th word of `u` holds the `i`-th
+    // word of each lane.
+    let mut u = [W::splat(0); 8];
+
+    for (i, word) in u.iter_mut().enumerate() {
+        let mut lanes = [0; MAX_LANES];
+
+        for (lane, first) in lanes.iter_mut().zip(first) {
+            *lane = u32::from_be_bytes([
+                first[4 * i],
+                first[4 * i + 1],
+                first[4 * i + 2],
+                first[4 * i + 3],
+            ]);
+        }
+
+        *word = W::load(&lanes);
+        lanes.zeroize();
+    }
+
+    let inner = states.inner.map(W::splat);
+    let outer = states.outer.map(W::splat);
+    let mut acc = u;
+
+    // Both hashed messages are made of the 64-byte padded key followed by 32
+    // bytes, so their last block always has the same padding.
+    let mut block = [W::splat(0); 16];
+
+    block[8] = W::splat(0x8000_0000);
+    block[15] = W::splat((64 + OUTPUT_LEN as u32) * 8);
+
+    for _ in 1..iterations {
+        block[..8].copy_from_slice(&u);
+
+        let mut state = inner;
+
+        compress(&mut state, &block);
+        block[..8].copy_from_slice(&state);
+
+        u = outer;
+        compress(&mut u, &block);
+
+        for (acc, u) in acc.iter_mut().zip(&u) {
+            *acc = acc.xor(*u);
+        }
+    }
+
+    for (i, word) in acc.iter().enumerate() {
+        let mut lanes = [0; MAX_LANES];
+
+        word.store(&mut lanes);
+
+        for (lane, result) in lanes.iter().zip(result.iter_mut()) {
+            result[4 * i..4 * i + 4].copy_from_slice(&lane.to_be_bytes());
+        }
+
+        lanes.zeroize();
+    }
+
+    // Words are not zeroized since they may be stored in registers.
+}
+
+/// A vector of 32-bit words, one per lane.
+trait Word: Copy {
+    /// The number of lanes of the vector.
+    const LANES: usize;
+
+    /// Returns a vector with `x` in all lanes.
+    fn splat(x: u32) -> Self;
+
+    /// Returns a vector with the first [`Word::LANES`] values of `lanes`.
+    fn load(lanes: &[u32; MAX_LANES]) -> Self;
+
+    /// Writes the lanes of the vector to the first [`Word::LANES`] values of
+    /// `lanes`.
+    fn store(self, lanes: &mut [u32; MAX_LANES]);
+
+    fn add(self, other: Self) -> Self;
+    fn and(self, other: Self) -> Self;
+    fn or(self, other: Self) -> Self;
+    fn xor(self, other: Self) -> Self;
+
+    /// Returns `!self & other`.
+    fn and_not(self, other: Self) -> Self;
+
+    /// Returns `self >> R`.
+    fn shr<const R: i32>(self) -> Self;
+
+    /// Returns `self.rotate_right(R)`, where `L` must be `32 - R`.
+    fn rotr<const R: i32, const L: i32>(self) -> Self;
+}
+
+/// The SHA-256 compression function, applied to each lane of `state` and
+/// `block`.
+#[inline(always)]
+fn compress<W: Word>(state: &mut [W; 8], block: &[W; 16]) {
+    // Compute the whole message schedule first, so that rounds below only
+    // depend on constant indices once unrolled.
+    let mut w = [W::splat(0); 64];
+
+    w[..16].copy_from_slice(block);
+
+    for i in 16..64 {
+        let s0 = w[i - 15]
+            .rotr::<7, 25>()
+            .xor(w[i - 15].rotr::<18, 14>())
+            .xor(w[i - 15].shr::<3>());
+        let s1 = w[i - 2]
+            .rotr::<17, 15>()
+            .xor(w[i - 2].rotr::<19, 13>())
+            .xor(w[i - 2].shr::<10>());
+
+        w[i] = w[i - 16].add(s0).add(w[i - 7]).add(s1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+
+    // Updates `d` and `h` for round `i`; rounds are then applied with
+    // variables rotated, which avoids moving them around.
+    macro_rules! round {
+        ($a:ident, $b:ident, $c:ident, $d:ident, $e:ident, $f:ident, $g:ident, $h:ident, $i:expr) => {
+            let s1 = $e
+                .rotr::<6, 26>()
+                .xor($e.rotr::<11, 21>())
+                .xor($e.rotr::<25, 7>());
+            let ch = $e.and($f).xor($e.and_not($g));
+            let t1 = $h.add(s1).add(ch).add(W::splat(K[$i])).add(w[$i]);
+            let s0 = $a
+                .rotr::<2, 30>()
+                .xor($a.rotr::<13, 19>())
+                .xor($a.rotr::<22, 10>());
+            let maj = $a.and($b).or($c.and($a.or($b)));
+
+            $d = $d.add(t1);
+            $h = t1.add(s0.add(maj));
+        };
+    }
+
+    for i in (0..64).step_by(8) {
+        round!(a, b, c, d, e, f, g, h, i);
+        round!(h, a, b, c, d, e, f, g, i + 1);
+        round!(g, h, a, b, c, d, e, f, i + 2);
+        round!(f, g, h, a, b, c, d, e, i + 3);
+        round!(e, f, g, h, a, b, c, d, i + 4);
+        round!(d, e, f, g, h, a, b, c, i + 5);
+        round!(c, d, e, f, g, h, a, b, i + 6);
+        round!(b, c, d, e, f, g, h, a, i + 7);
+    }
+
+    for (word, x) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+        *word = word.add(x);
+    }
+}
+
+/// Portable implementation of [`Word`], used on architectures without a
+/// SIMD implementation and to compute [`HmacStates`].
+impl<const N: usize> Word for [u32; N] {
+    const LANES: usize = N;
+
+    #[inline(always)]
+    fn splat(x: u32) -> Self {
+        [x; N]
+    }
+
+    #[inline(always)]
+    fn load(lanes: &[u32; MAX_LANES]) -> Self {
+        core::array::from_fn(|i| lanes[i])
+    }
+
+    #[inline(always)]
+    fn store(self, lanes: &mut [u32; MAX_LANES]) {
+        lanes[..N].copy_from_slice(&self);
+    }
+
+    #[inline(always)]
+    fn add(self, other: Self) -> Self {
+        core::array::from_fn(|i| self[i].wrapping_add(other[i]))
+    }
+
+    #[inline(always)]
+    fn and(self, other: Self) -> Self {
+        core::array::from_fn(|i| self[i] & other[i])
+    }
+
+    #[inline(always)]
+    fn or(self, other: Self) -> Self {
+        core::array::from_fn(|i| self[i] | other[i])
+    }
+
+    #[inline(always)]
+    fn xor(self, other: Self) -> Self {
+        core::array::from_fn(|i| self[i] ^ other[i])
+    }
+
+    #[inline(always)]
+    fn and_not(self, other: Self) -> Self {
+        core::array::from_fn(|i| !self[i] & other[i])
+    }
+
+    #[inline(always)]
+    fn shr<const R: i32>(self) -> Self {
+        self.map(|x| x >> R)
+    }
+
+    #[inline(always)]
+    fn rotr<const R: i32, const L: i32>(self) -> Self {
+        self.map(|x| x.rotate_right(R as u32))
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86_64 {
+    use core::arch::x86_64::*;
+
+    use super::{Word, MAX_LANES};
+
+    /// 4 lanes processed with SSE2, which is always available on x86-64.
+    #[derive(Clone, Copy)]
+    pub(super) struct Sse2(__m128i);
+
+    // SAFETY (for all functions below): SSE2 is always available on x86-64.
+    impl Word for Sse2 {
+        const LANES: usize = 4;
+
+        #[inline(always)]
+        fn splat(x: u32) -> Self {
+            Self(unsafe { _mm_set1_epi32(x as i32) })
+        }
+
+        #[inline(always)]
+        fn load(lanes: &[u32; MAX_LANES]) -> Self {
+            Self(unsafe { _mm_loadu_si128(lanes.as_ptr().cast()) })
+        }
+
+        #[inline(always)]
+        fn store(self, lanes: &mut [u32; MAX_LANES]) {
+            unsafe { _mm_storeu_si128(lanes.as_mut_ptr().cast(), self.0) }
+        }
+
+        #[inline(always)]
+        fn add(self, other: Self) -> Self {
+            Self(unsafe { _mm_add_epi32(self.0, other.0) })
+        }
+
+        #[inline(always)]
+        fn and(self, other: Self) -> Self {
+            Self(unsafe { _mm_and_si128(self.0, other.0) })
+        }
+
+        #[inline(always)]
+        fn or(self, other: Self) -> Self {
+            Self(unsafe { _mm_or_si128(self.0, other.0) })
+        }
+
+        #[inline(always)]
+        fn xor(self, other: Self) -> Self {
+            Self(unsafe { _mm_xor_si128(self.0, other.0) })
+        }
+
+        #[inline(always)]
+        fn and_not(self, other: Self) -> Self {
+            Self(unsafe { _mm_andnot_si128(self.0, other.0) })
+        }
+
+        #[inline(always)]
+        fn shr<const R: i32>(self) -> Self {
+            Self(unsafe { _mm_srli_epi32::<R>(self.0) })
+        }
+
+        #[inline(always)]
+        fn rotr<const R: i32, const L: i32>(self) -> Self {
+            Self(unsafe { _mm_or_si128(_mm_srli_epi32::<R>(self.0), _mm_slli_epi32::<L>(self.0)) })
+        }
+    }
+
+    pub(super) use self::avx2::iterate_avx2;
+
+    /// Processing with AVX2, in its own module so that the `Avx2` type cannot
+    /// be used outside of `iterate_avx2`.
+    mod avx2 {
+        use core::arch::x86_64::*;
+
+        use super::super::{iterate_lanes, HmacStates, Word, MAX_LANES, OUTPUT_LEN};
+
+        /// Same as [`iterate`](super::super::iterate), with 8 lanes.
+        ///
+        /// # Safety
+        ///
+        /// AVX2 must be available.
+        #[target_feature(enable = "avx2")]
+        pub(in super::super) unsafe fn iterate_avx2(
+            states: &HmacStates,
+            iterations: u32,
+            first: &[[u8; OUTPUT_LEN]],
+            result: &mut [[u8; OUTPUT_LEN]],
+        ) {
+            iterate_lanes::<Avx2>(states, iterations, first, result)
+        }
+
+        /// 8 lanes processed with AVX2.
+        ///
+        /// This type is private to this module, whose only entry point is
+        /// [`iterate_avx2`], so values of this type only exist while AVX2 is
+        /// known to be available.
+        #[derive(Clone, Copy)]
+        struct Avx2(__m256i);
+
+        // SAFETY (for all functions below): `Avx2` can only be used through
+        // `iterate_avx2`, whose callers guarantee that AVX2 is available. This
+        // does not depend on these functions being inlined into
+        // `iterate_avx2`: calling AVX2 intrinsics from a function without the
+        // `avx2` target feature is sound on a CPU that supports AVX2.
+        impl Word for Avx2 {
+            const LANES: usize = 8;
+
+            #[inline(always)]
+            fn splat(x: u32) -> Self {
+                Self(unsafe { _mm256_set1_epi32(x as i32) })
+            }
+
+            #[inline(always)]
+            fn load(lanes: &[u32; MAX_LANES]) -> Self {
+                Self(unsafe { _mm256_loadu_si256(lanes.as_ptr().cast()) })
+            }
+
+            #[inline(always)]
+            fn store(self, lanes: &mut [u32; MAX_LANES]) {
+                unsafe { _mm256_storeu_si256(lanes.as_mut_ptr().cast(), self.0) }
+            }
+
+            #[inline(always)]
+            fn add(self, other: Self) -> Self {
+                Self(unsafe { _mm256_add_epi32(self.0, other.0) })
+            }
+
+            #[inline(always)]
+            fn and(self, other: Self) -> Self {
+                Self(unsafe { _mm256_and_si256(self.0, other.0) })
+            }
+
+            #[inline(always)]
+            fn or(self, other: Self) -> Self {
+                Self(unsafe { _mm256_or_si256(self.0, other.0) })
+            }
+
+            #[inline(always)]
+            fn xor(self, other: Self) -> Self {
+                Self(unsafe { _mm256_xor_si256(self.0, other.0) })
+            }
+
+            #[inline(always)]
+            fn and_not(self, other: Self) -> Self {
+                Self(unsafe { _mm256_andnot_si256(self.0, other.0) })
+            }
+
+            #[inline(always)]
+            fn shr<const R: i32>(self) -> Self {
+                Self(unsafe { _mm256_srli_epi32::<R>(self.0) })
+            }
+
+            #[inline(always)]
+            fn rotr<const R: i32, const L: i32>(self) -> Self {
+                Self(unsafe {
+                    _mm256_or_si256(
+                        _mm256_srli_epi32::<R>(self.0),
+                        _mm256_slli_epi32::<L>(self.0),
+                    )
+                })
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use pbkdf2::hmac::digest::KeyInit;
+
+    /// Runs [`pbkdf2`] and compares its result with the `pbkdf2` crate.
+    fn check(password: &[u8], salts: &[&[u8]], iterations: u32, len: usize) {
+        let mac = Hmac::<Sha256>::new_from_slice(password).unwrap();
+        let states = HmacStates::new(password);
+        let mut outputs = std::vec![std::vec![0; len]; salts.len()];
+
+        pbkdf2(&mac, &states, salts, iterations, &mut outputs);
+
+        for (salt, output) in salts.iter().zip(&outputs) {
+            let mut expected = std::vec![0; len];
+
+            pbkdf2::pbkdf2_hmac::<Sha256>(password, salt, iterations, &mut expected);
+
+            assert_eq!(output, &expected);
+        }
+    }
+
+    #[test]
+    fn matches_pbkdf2() {
+        let salts: [&[u8]; 11] = [
+            b"a",
+            b"bb",
+            b"ccc",
+            b"dddd",
+            b"eeeee",
+            b"ffffff",
+            b"ggggggg",
+            b"hhhhhhhh",
+            b"iiiiiiiii",
+            b"jjjjjjjjjj",
+            &[0x42; 100],
+        ];
+
+        for password in [&b"password"[..], &[0x13; 64], &[0x37; 65]] {
+            for iterations in [1, 2, 3, 100] {
+                for lanes in [1, 3, 4, 5, 8, 11] {
+                    check(password, &salts[..lanes], iterations, 32);
+                }
+
+                check(password, &salts, iterations, 100);
+            }
+        }
+    }
+
+    #[test]
+    fn portable_matches_simd() {
+        let states = HmacStates::new(b"password");
+        let first = [
+            [0x5a; OUTPUT_LEN],
+            [0x42; OUTPUT_LEN],
+            [0; OUTPUT_LEN],
+            [0xff; OUTPUT_LEN],
+        ];
+        let mut expected = [[0; OUTPUT_LEN]; 4];
+        let mut result = [[0; OUTPUT_LEN]; 4];
+
+        iterate_lanes::<[u32; 4]>(&states, 10, &first, &mut expected);
+        iterate(&states, 10, &first, &mut result);
+
+        assert_eq!(result, expected);
+    }
+}
@@ -4,16 +4,84 @@
 #[cfg(feature = "std")]
 extern crate std;
 
+use core::iter;
 use core::mem::MaybeUninit;
+use core::ops::ControlFlow;
 
-use pbkdf2::{
-    hmac::{Hmac, Mac as _},
-    pbkdf2_hmac,
-};
-use sha2::{Sha256, Sha384, Sha512};
+use pbkdf2::hmac::{Hmac, Mac as _};
+use sha2::Sha256;
+use zeroize::Zeroize;
 
-/// Selects the hash algorithm to use in PBKDF2.
+use self::ct::Quotient;
+
+mod alphabet;
+#[cfg(feature = "std")]
+mod batch;
+#[cfg(feature = "std")]
+mod calibrate;
+mod ct;
+mod error;
+mod extended;
+mod kdf;
+mod lanes;
+mod master_key;
+#[cfg(feature = "unicode-normalization")]
+mod normalization;
+mod passphrase;
+mod pin;
+mod policy;
+mod profile;
+#[cfg(feature = "std")]
+mod rules;
+mod secret;
+#[cfg(feature = "std")]
+mod strength;
+mod v1;
+mod website;
+mod wordlist;
+
+pub use self::alphabet::{Alphabet, AMBIGUOUS_CHARACTERS, MAX_ALPHABET_CLASSES};
+#[cfg(feature = "std")]
+pub use self::batch::generate_batch;
+#[cfg(feature = "std")]
+pub use self::calibrate::{calibrate_iterations, try_calibrate_iterations};
+pub use self::error::Error;
+pub use self::extended::*;
+#[cfg(feature = "argon2")]
+pub use self::kdf::Argon2Params;
+pub use self::kdf::Kdf;
+#[cfg(feature = "scrypt")]
+pub use self::kdf::ScryptParams;
+pub use self::master_key::MasterKey;
+#[cfg(feature = "unicode-normalization")]
+pub use self::normalization::{normalize_unicode, NormalizationForm};
+pub use self::passphrase::*;
+pub use self::pin::*;
+pub use self::policy::{PasswordPolicy, MAX_POLICY_ATTEMPTS};
+pub use self::profile::Profile;
+#[cfg(feature = "std")]
+pub use self::rules::PasswordRules;
+pub use self::secret::Entropy;
+#[cfg(feature = "std")]
+pub use self::secret::{MasterPassword, Password};
+#[cfg(feature = "std")]
+pub use self::strength::{password_strength, try_password_strength, Strength};
+pub use self::v1::*;
+#[cfg(feature = "std")]
+pub use self::website::normalize_website;
+#[cfg(feature = "psl")]
+pub use self::website::registrable_domain;
+#[cfg(feature = "idna")]
+pub use self::website::{canonicalize_domain, DomainForm};
+pub use self::wordlist::{BIP39_WORDLIST, DEFAULT_WORDLIST};
+
+/// Selects the hash algorithm to use in PBKDF2, or the key derivation function
+/// to use instead of PBKDF2.
+///
+/// This enum is non-exhaustive since the `argon2` and `scrypt` features add
+/// variants to it, so matching on it requires a wildcard arm.
 #[derive(PartialEq, Eq, Clone, Copy)]
+#[non_exhaustive]
 pub enum Algorithm {
     /// SHA2-256.
     ///
@@ -31,6 +99,48 @@ pub enum Algorithm {
     /// Note: Using this algorithm makes the generated passwords different from
     /// every other LessPass implementation.
     SHA512,
+
+    /// Argon2id with the given parameters, instead of PBKDF2. The number of
+    /// iterations given alongside the algorithm is ignored.
+    ///
+    /// Note: Using this algorithm makes the generated passwords different from
+    /// every other LessPass implementation.
+    #[cfg(feature = "argon2")]
+    Argon2id(Argon2Params),
+
+    /// scrypt with the given parameters, instead of PBKDF2. The number of
+    /// iterations given alongside the algorithm is ignored.
+    ///
+    /// Note: Using this algorithm makes the generated passwords different from
+    /// every other LessPass implementation.
+    #[cfg(feature = "scrypt")]
+    Scrypt(ScryptParams),
+}
+
+impl Algorithm {
+    /// Returns the length in bytes of the entropy generated by
+    /// [`generate_entropy`] for this algorithm, i.e. the output size of its
+    /// hash function (or 32 bytes for Argon2id and scrypt).
+    pub const fn entropy_len(self) -> usize {
+        match self {
+            Algorithm::SHA256 => 256 / 8,
+            Algorithm::SHA384 => 384 / 8,
+            Algorithm::SHA512 => 512 / 8,
+            #[cfg(feature = "argon2")]
+            Algorithm::Argon2id(_) => 256 / 8,
+            #[cfg(feature = "scrypt")]
+            Algorithm::Scrypt(_) => 256 / 8,
+        }
+    }
+
+    /// Returns whether this algorithm uses PBKDF2, and thus the number of
+    /// iterations given alongside it.
+    pub const fn is_pbkdf2(self) -> bool {
+        matches!(
+            self,
+            Algorithm::SHA256 | Algorithm::SHA384 | Algorithm::SHA512
+        )
+    }
 }
 
 bitflags::bitflags! {
@@ -50,10 +160,14 @@ bitflags::bitflags! {
 }
 
 impl CharacterSet {
-    const LOWERCASE: &'static str = "abcdefghijklmnopqrstuvwxyz";
-    const UPPERCASE: &'static str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
-    const DIGITS: &'static str = "0123456789";
-    const SYMBOLS: &'static str = "!\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
+    /// The characters used by [`CharacterSet::Lowercase`].
+    pub const LOWERCASE: &'static str = "abcdefghijklmnopqrstuvwxyz";
+    /// The characters used by [`CharacterSet::Uppercase`].
+    pub const UPPERCASE: &'static str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+    /// The characters used by [`CharacterSet::Digits`].
+    pub const DIGITS: &'static str = "0123456789";
+    /// The characters used by [`CharacterSet::Symbols`].
+    pub const SYMBOLS: &'static str = "!\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
 
     /// Returns a string that contains all the characters that may be used to
     /// generate a password.
@@ -141,13 +255,148 @@ pub fn generate_salt_to_uninit<'out>(
     counter: u32,
     output: &'out mut [MaybeUninit<u8>],
 ) -> Result<&'out mut [u8], usize> {
-    let mut counter_buf = [MaybeUninit::uninit(); 8];
+    write_salt(website, username, counter.into(), false, output)
+}
+
+/// Same as [`generate_salt_to`], but directly returns the salt instead of
+/// requiring a mutable output buffer.
+#[cfg(feature = "std")]
+#[inline]
+pub fn generate_salt(website: &str, username: &str, counter: u32) -> std::vec::Vec<u8> {
+    salt_vec(website, username, counter.into(), false)
+}
+
+/// The maximum counter supported by [`generate_strict_salt_to`], i.e. the
+/// largest integer that JavaScript numbers represent exactly
+/// (`Number.MAX_SAFE_INTEGER`).
+pub const MAX_STRICT_COUNTER: u64 = (1 << 53) - 1;
+
+/// Same as [`generate_salt_to`], but strictly follows the canonical LessPass
+/// implementation on edge cases, and accepts counters up to
+/// [`MAX_STRICT_COUNTER`].
+///
+/// The salt is the concatenation of the website, the login and the counter
+/// written in lowercase hexadecimal, as done by `counter.toString(16)` in
+/// JavaScript. Unlike [`generate_salt_to`], which writes nothing for a
+/// counter of 0 (and thus generates a different password than other LessPass
+/// implementations), the counter 0 is written as `"0"`:
+///
+/// ```
+/// use lesspass::{generate_salt_to, generate_strict_salt_to};
+///
+/// let mut salt = [0; 32];
+///
+/// assert_eq!(generate_strict_salt_to("example.org", "", 0, &mut salt), Ok(12));
+/// assert_eq!(&salt[..12], b"example.org0");
+/// assert_eq!(generate_salt_to("example.org", "", 0, &mut salt), Ok(11));
+/// ```
+///
+/// The website and login are used verbatim, as LessPass does: empty and
+/// whitespace-only logins are valid, and surrounding whitespace is kept.
+/// Empty master passwords are still rejected when generating the entropy.
+///
+/// # Panics
+///
+/// Panics if `counter` is greater than [`MAX_STRICT_COUNTER`]. See
+/// [`try_generate_strict_salt_to`] for a version of this function that
+/// returns an [`Error`] instead.
+#[track_caller]
+#[inline]
+pub fn generate_strict_salt_to(
+    website: &str,
+    username: &str,
+    counter: u64,
+    output: &mut [u8],
+) -> Result<usize, usize> {
+    match try_generate_strict_salt_to(website, username, counter, output) {
+        Ok(written) => Ok(written),
+        Err(Error::BufferTooSmall { required }) => Err(required),
+        Err(err) => error::unwrap(Err(err)),
+    }
+}
+
+/// Same as [`generate_strict_salt_to`], but returns an [`Error`] instead of
+/// panicking if `counter` is out of range.
+///
+/// If `output` is too small, [`Error::BufferTooSmall`] is returned.
+pub fn try_generate_strict_salt_to(
+    website: &str,
+    username: &str,
+    counter: u64,
+    output: &mut [u8],
+) -> Result<usize, Error> {
+    check_strict_counter(counter)?;
+
+    write_salt(
+        website,
+        username,
+        counter,
+        true,
+        slice_to_maybe_uninit_mut(output),
+    )
+    .map(|x| x.len())
+    .map_err(|required| Error::BufferTooSmall { required })
+}
+
+/// Same as [`generate_strict_salt_to`], but directly returns the salt instead
+/// of requiring a mutable output buffer.
+///
+/// # Panics
+///
+/// Panics if `counter` is greater than [`MAX_STRICT_COUNTER`]. See
+/// [`try_generate_strict_salt`] for a version of this function that returns
+/// an [`Error`] instead.
+#[cfg(feature = "std")]
+#[track_caller]
+#[inline]
+pub fn generate_strict_salt(website: &str, username: &str, counter: u64) -> std::vec::Vec<u8> {
+    error::unwrap(try_generate_strict_salt(website, username, counter))
+}
+
+/// Same as [`generate_strict_salt`], but returns an [`Error`] instead of
+/// panicking if `counter` is out of range.
+#[cfg(feature = "std")]
+pub fn try_generate_strict_salt(
+    website: &str,
+    username: &str,
+    counter: u64,
+) -> Result<std::vec::Vec<u8>, Error> {
+    check_strict_counter(counter)?;
+
+    Ok(salt_vec(website, username, counter, true))
+}
+
+/// Returns an [`Error`] if `counter` cannot be used in strict salts.
+pub(crate) fn check_strict_counter(counter: u64) -> Result<(), Error> {
+    if counter > MAX_STRICT_COUNTER {
+        return Err(Error::CounterOutOfRange {
+            max: MAX_STRICT_COUNTER,
+            got: counter,
+        });
+    }
+
+    Ok(())
+}
+
+/// Writes the salt of the given website, login and counter to `output`.
+///
+/// In `strict` mode, the counter 0 is written as `"0"` like in the canonical
+/// LessPass implementation; otherwise it is omitted, which is what
+/// [`generate_salt_to`] has always done.
+pub(crate) fn write_salt<'out>(
+    website: &str,
+    username: &str,
+    counter: u64,
+    strict: bool,
+    output: &'out mut [MaybeUninit<u8>],
+) -> Result<&'out mut [u8], usize> {
+    let mut counter_buf = [MaybeUninit::uninit(); 16];
     let counter = {
-        let mut counter = counter as usize;
+        let mut counter = counter;
         let mut i = counter_buf.len();
 
-        while counter != 0 {
-            counter_buf[i - 1].write(b"0123456789abcdef"[counter & 0xf]);
+        while counter != 0 || (strict && i == counter_buf.len()) {
+            counter_buf[i - 1].write(b"0123456789abcdef"[(counter & 0xf) as usize]);
             counter >>= 4;
             i -= 1;
         }
@@ -177,13 +426,17 @@ pub fn generate_salt_to_uninit<'out>(
     Ok(unsafe { &mut *(&mut output[..offset] as *mut [MaybeUninit<u8>] as *mut [u8]) })
 }
 
-/// Same as [`generate_salt_to`], but directly returns the salt instead of
-/// requiring a mutable output buffer.
+/// Returns the salt of the given website, login and counter; see
+/// [`write_salt`].
 #[cfg(feature = "std")]
-#[inline]
-pub fn generate_salt(website: &str, username: &str, counter: u32) -> std::vec::Vec<u8> {
+pub(crate) fn salt_vec(
+    website: &str,
+    username: &str,
+    counter: u64,
+    strict: bool,
+) -> std::vec::Vec<u8> {
     let mut counter_copy = counter;
-    let mut counter_len = 0;
+    let mut counter_len = usize::from(strict && counter == 0);
 
     while counter_copy != 0 {
         counter_copy >>= 4;
@@ -191,14 +444,13 @@ pub fn generate_salt(website: &str, username: &str, counter: u32) -> std::vec::V
     }
 
     let mut uninit_output = uninit_vec(website.len() + username.len() + counter_len);
-    let result = generate_salt_to_uninit(website, username, counter, &mut uninit_output);
+    let result = write_salt(website, username, counter, strict, &mut uninit_output);
 
     // Make sure that the result (the initialized subslice of `uninit_output`)
     // has the same length as `uninit_output` itself.
     debug_assert_eq!(result.map(|x| x.len()), Ok(uninit_output.len()));
 
-    // SAFETY: `uninit_output` was fully initialized in
-    // `generate_salt_to_uninit`.
+    // SAFETY: `uninit_output` was fully initialized in `write_salt`.
     unsafe { assume_init_vec(uninit_output) }
 }
 
@@ -217,70 +469,235 @@ pub const MAX_ENTROPY_LEN: usize = 64;
 /// # Panics
 ///
 /// Panics if `output` is smaller than [`MIN_ENTROPY_LEN`] or greater than
-/// [`MAX_ENTROPY_LEN`], or if `master_password` is empty, or if `iterations` is
-/// 0, or if `salt` is empty.
+/// [`MAX_ENTROPY_LEN`], or if `master_password` is empty, or if `salt` is
+/// empty, or if [`Kdf::derive`] fails (e.g. because `iterations` is 0 with
+/// PBKDF2). See [`try_generate_entropy_to`] for a version of this function
+/// that returns an [`Error`] instead.
+#[track_caller]
+#[inline]
 pub fn generate_entropy_to(
     master_password: &str,
     salt: &[u8],
-    algorithm: Algorithm,
+    algorithm: impl Kdf,
     iterations: u32,
     output: &mut [u8],
 ) {
-    assert!(!master_password.is_empty());
-    assert!(!salt.is_empty());
-    assert!(iterations > 0);
-    assert!((MIN_ENTROPY_LEN..=MAX_ENTROPY_LEN).contains(&output.len()));
-
-    match algorithm {
-        Algorithm::SHA256 => {
-            pbkdf2_hmac::<Sha256>(master_password.as_bytes(), salt, iterations, output)
-        }
-        Algorithm::SHA384 => {
-            pbkdf2_hmac::<Sha384>(master_password.as_bytes(), salt, iterations, output)
-        }
-        Algorithm::SHA512 => {
-            pbkdf2_hmac::<Sha512>(master_password.as_bytes(), salt, iterations, output)
-        }
+    error::unwrap(try_generate_entropy_to(
+        master_password,
+        salt,
+        algorithm,
+        iterations,
+        output,
+    ))
+}
+
+/// Same as [`generate_entropy_to`], but returns an [`Error`] instead of
+/// panicking if its inputs are invalid.
+#[inline]
+pub fn try_generate_entropy_to(
+    master_password: &str,
+    salt: &[u8],
+    algorithm: impl Kdf,
+    iterations: u32,
+    output: &mut [u8],
+) -> Result<(), Error> {
+    derive_entropy(
+        master_password,
+        salt,
+        &algorithm,
+        iterations,
+        output,
+        MAX_ENTROPY_LEN,
+    )
+}
+
+/// Runs the given key derivation function after validating its inputs,
+/// allowing up to `max_len` bytes of entropy to be generated.
+fn derive_entropy(
+    master_password: &str,
+    salt: &[u8],
+    kdf: &dyn Kdf,
+    iterations: u32,
+    output: &mut [u8],
+    max_len: usize,
+) -> Result<(), Error> {
+    if master_password.is_empty() {
+        return Err(Error::EmptyMasterPassword);
+    }
+
+    check_entropy_args(salt, output.len(), max_len)?;
+
+    kdf.derive(master_password, salt, iterations, output)
+}
+
+/// Returns an [`Error`] if `len` bytes of entropy (with `len` at most
+/// `max_len`) cannot be derived from `salt`.
+fn check_entropy_args(salt: &[u8], len: usize, max_len: usize) -> Result<(), Error> {
+    if salt.is_empty() {
+        return Err(Error::EmptySalt);
+    }
+    if !(MIN_ENTROPY_LEN..=max_len).contains(&len) {
+        return Err(Error::EntropyLengthOutOfRange {
+            min: MIN_ENTROPY_LEN,
+            max: max_len,
+            got: len,
+        });
     }
+
+    Ok(())
 }
 
 /// Same as [`generate_entropy_to`], but directly returns the entropy buffer
 /// instead of requiring a mutable output buffer.
 ///
-/// The length of the resulting buffer is:
+/// The length of the resulting buffer is given by [`Kdf::entropy_len`]; for
+/// built-in algorithms, it is:
 /// - 32 bytes for SHA-256,
 /// - 48 bytes for SHA-384,
-/// - 64 bytes for SHA-512.
+/// - 64 bytes for SHA-512,
+/// - 32 bytes for Argon2id and scrypt.
 #[cfg(feature = "std")]
+#[track_caller]
 #[inline]
 pub fn generate_entropy(
     master_password: &str,
     salt: &[u8],
-    algorithm: Algorithm,
+    algorithm: impl Kdf,
     iterations: u32,
 ) -> std::vec::Vec<u8> {
-    let out_len = match algorithm {
-        Algorithm::SHA256 => 256 / 8,
-        Algorithm::SHA384 => 384 / 8,
-        Algorithm::SHA512 => 512 / 8,
-    };
-    let mut out = std::vec![0; out_len];
+    error::unwrap(try_generate_entropy(
+        master_password,
+        salt,
+        algorithm,
+        iterations,
+    ))
+}
 
-    generate_entropy_to(master_password, salt, algorithm, iterations, &mut out);
+/// Same as [`generate_entropy`], but returns an [`Error`] instead of
+/// panicking if its inputs are invalid.
+#[cfg(feature = "std")]
+pub fn try_generate_entropy(
+    master_password: &str,
+    salt: &[u8],
+    algorithm: impl Kdf,
+    iterations: u32,
+) -> Result<std::vec::Vec<u8>, Error> {
+    let mut out = std::vec![0; algorithm.entropy_len()];
 
-    out
+    try_generate_entropy_to(master_password, salt, algorithm, iterations, &mut out)?;
+
+    Ok(out)
 }
 
-// Wrap type definition in a private module to use `#[allow(...)]`.
-#[allow(clippy::all)]
-mod private {
-    uint::construct_uint! {
-        /// A 512-bits integer.
-        pub(super) struct BigUint(8 /* 64-bit words */);
+/// Same as [`try_generate_extended_entropy_to`], but regularly calls
+/// `progress` with the percentage of the derivation that is complete (from 0
+/// to 100), which can be used to report the progress of slow derivations.
+///
+/// The derivation stops as soon as `progress` returns
+/// [`ControlFlow::Break`], in which case [`Error::Cancelled`] is returned and
+/// the content of `output` is unspecified. Since cancelling is expected
+/// here, this function returns an [`Error`] instead of panicking if its
+/// inputs are invalid, like [`try_generate_extended_entropy_to`].
+///
+/// With PBKDF2, `progress` is called every thousand iterations or so. Other
+/// key derivation functions cannot be interrupted, so `progress` is only
+/// called before and after the derivation.
+///
+/// ```
+/// use core::ops::ControlFlow;
+/// use std::time::{Duration, Instant};
+///
+/// use lesspass::{generate_salt, try_generate_entropy_with_progress_to, Algorithm, Error};
+///
+/// let salt = generate_salt("example.org", "contact@example.org", 1);
+/// let mut entropy = [0; 32];
+/// let start = Instant::now();
+///
+/// // Give up if the derivation takes more than 10 milliseconds.
+/// let result = try_generate_entropy_with_progress_to(
+///     "password",
+///     &salt,
+///     Algorithm::SHA256,
+///     100_000_000,
+///     &mut entropy,
+///     |percent| {
+///         println!("{percent}%");
+///
+///         if start.elapsed() < Duration::from_millis(10) {
+///             ControlFlow::Continue(())
+///         } else {
+///             ControlFlow::Break(())
+///         }
+///     },
+/// );
+///
+/// assert_eq!(result, Err(Error::Cancelled));
+/// ```
+pub fn try_generate_entropy_with_progress_to(
+    master_password: &str,
+    salt: &[u8],
+    algorithm: impl Kdf,
+    iterations: u32,
+    output: &mut [u8],
+    mut progress: impl FnMut(u8) -> ControlFlow<()>,
+) -> Result<(), Error> {
+    if master_password.is_empty() {
+        return Err(Error::EmptyMasterPassword);
+    }
+
+    check_entropy_args(salt, output.len(), MAX_EXTENDED_ENTROPY_LEN)?;
+
+    match algorithm.as_algorithm() {
+        Some(pbkdf2) if pbkdf2.is_pbkdf2() => {
+            if iterations == 0 {
+                return Err(Error::ZeroIterations);
+            }
+
+            MasterKey::try_new(master_password, pbkdf2)?.derive_with_progress(
+                salt,
+                iterations,
+                output,
+                &mut progress,
+            )
+        }
+        _ => {
+            if progress(0).is_break() {
+                return Err(Error::Cancelled);
+            }
+
+            algorithm.derive(master_password, salt, iterations, output)?;
+
+            match progress(100) {
+                ControlFlow::Continue(()) => Ok(()),
+                ControlFlow::Break(()) => Err(Error::Cancelled),
+            }
+        }
     }
 }
 
-use self::private::BigUint;
+/// Same as [`try_generate_entropy_with_progress_to`], but directly returns the
+/// entropy buffer, whose length is given by [`Kdf::entropy_len`].
+#[cfg(feature = "std")]
+pub fn try_generate_entropy_with_progress(
+    master_password: &str,
+    salt: &[u8],
+    algorithm: impl Kdf,
+    iterations: u32,
+    progress: impl FnMut(u8) -> ControlFlow<()>,
+) -> Result<std::vec::Vec<u8>, Error> {
+    let mut out = std::vec![0; algorithm.entropy_len()];
+
+    try_generate_entropy_with_progress_to(
+        master_password,
+        salt,
+        algorithm,
+        iterations,
+        &mut out,
+        progress,
+    )?;
+
+    Ok(out)
+}
 
 /// The minimum length of the rendered password, inclusive.
 pub const MIN_PASSWORD_LEN: usize = 5;
@@ -290,90 +707,220 @@ pub const MAX_PASSWORD_LEN: usize = 35;
 
 /// Same as [`render_password_to`], but works with an uninitialized output
 /// buffer, which is okay since it only writes to it, without reading from it.
-pub fn render_password_to_uninit<'out>(
+#[track_caller]
+#[inline]
+pub fn render_password_to_uninit<'a, 'out>(
     entropy: &[u8],
-    charset: CharacterSet,
+    charset: impl Into<Alphabet<'a>>,
     output: &'out mut [MaybeUninit<u8>],
 ) -> &'out mut [u8] {
-    assert!(!entropy.is_empty());
-    assert!(!charset.is_empty());
+    error::unwrap(try_render_password_to_uninit(entropy, charset, output))
+}
 
-    let len = output.len();
+/// Same as [`render_password_to_uninit`], but returns an [`Error`] instead of
+/// panicking if its inputs are invalid.
+pub fn try_render_password_to_uninit<'a, 'out>(
+    entropy: &[u8],
+    charset: impl Into<Alphabet<'a>>,
+    output: &'out mut [MaybeUninit<u8>],
+) -> Result<&'out mut [u8], Error> {
+    let alphabet = charset.into();
 
-    assert!((MIN_PASSWORD_LEN..=MAX_PASSWORD_LEN).contains(&len));
+    check_render_args(
+        entropy,
+        &alphabet,
+        output.len(),
+        MAX_ENTROPY_LEN,
+        MAX_PASSWORD_LEN,
+    )?;
 
-    let chars = charset.get_characters().as_bytes();
-    let (sets, sets_len) = charset.get_sets();
+    let mut quotient = Quotient::from_be_bytes(entropy);
 
-    let mut offset = 0;
+    Ok(render_with(&alphabet, output, &mut quotient))
+}
 
-    // Generate initial part of the password.
-    let mut quotient = BigUint::from_big_endian(entropy);
+/// Renders a password in `output` using the characters in `alphabet`, and
+/// the remainders of the successive divisions of `quotient` (i.e. the
+/// entropy).
+///
+/// Characters are picked and inserted in constant time with respect to these
+/// remainders.
+///
+/// Arguments must have been validated by the caller.
+#[inline(always)]
+fn render_with<'out>(
+    alphabet: &Alphabet<'_>,
+    output: &'out mut [MaybeUninit<u8>],
+    quotient: &mut Quotient,
+) -> &'out mut [u8] {
+    let len = output.len();
+    let chars_len = alphabet.len();
+    let sets_len = alphabet.classes().len();
+    let initial_len = len - sets_len;
+
+    // Initialize the output so that characters can be inserted in constant
+    // time below.
+    for byte in output.iter_mut() {
+        byte.write(0);
+    }
 
-    for _ in 0..(len - sets_len) {
-        let rem = div_rem(&mut quotient, chars.len());
+    // SAFETY: all bytes in `output` were written to above.
+    let output = unsafe { &mut *(output as *mut [MaybeUninit<u8>] as *mut [u8]) };
 
-        output[offset].write(chars[rem]);
+    // All divisors are known in advance, which allows `digits` to divide the
+    // quotient by several of them at once.
+    let divisors = iter::repeat(chars_len)
+        .take(initial_len)
+        .chain((0..sets_len).map(|set| alphabet.class_len(set)))
+        .chain(initial_len..len);
+    let mut digits = quotient.digits(divisors);
+    let mut offset = 0;
+
+    // Generate initial part of the password.
+    for (out, rem) in output[..initial_len].iter_mut().zip(&mut digits) {
+        *out = alphabet.char_at(rem);
         offset += 1;
     }
 
     // Compute some random characters in each set in order to ensure all sets
     // will be used at least once.
-    let mut additional_chars = [0; 4];
+    let mut additional_chars = [0; MAX_ALPHABET_CLASSES];
     let mut additional_chars_len = 0;
 
-    for set in sets.into_iter().take(sets_len) {
-        let rem = div_rem(&mut quotient, set.len());
-
-        additional_chars[additional_chars_len] += set.as_bytes()[rem];
+    for (set, rem) in (0..sets_len).zip(&mut digits) {
+        additional_chars[additional_chars_len] = alphabet.class_char_at(set, rem);
         additional_chars_len += 1;
     }
 
     // Finalize last part of password using previously generated characters.
-    for ch in additional_chars.into_iter().take(additional_chars_len) {
-        let rem = div_rem(&mut quotient, offset);
-
+    for (&ch, rem) in additional_chars[..additional_chars_len]
+        .iter()
+        .zip(&mut digits)
+    {
         // Insert `ch` at `rem`.
-        output.copy_within(rem..output.len() - 1, rem + 1);
-        output[rem].write(ch);
+        ct::insert(output, offset, rem, ch);
 
         offset += 1;
     }
 
+    additional_chars.zeroize();
+
     debug_assert_eq!(offset, len);
 
-    // SAFETY: all bytes in `output` were written to (`offset == len`).
-    unsafe { &mut *(output as *mut [MaybeUninit<u8>] as *mut [u8]) }
+    output
 }
 
 /// Generates a password of the given length using the provided entropy and
 /// character sets, and writes it to `output`.
 ///
+/// `charset` is either a [`CharacterSet`] or a custom [`Alphabet`].
+///
+/// Rendering runs in constant time with respect to `entropy`: the entropy is
+/// divided limb by limb with a fixed number of iterations, and characters are
+/// picked and inserted without branching or indexing memory depending on it.
+///
 /// # Panics
 ///
-/// Panics if `output` is smaller than [`MIN_PASSWORD_LEN`] or greater than
-/// [`MAX_PASSWORD_LEN`], or if `entropy` is empty, or if `charset` is empty.
+/// Panics if `output` is smaller than [`MIN_PASSWORD_LEN`] (or than the number
+/// of classes in the alphabet) or greater than [`MAX_PASSWORD_LEN`], or if
+/// `entropy` is empty or longer than [`MAX_ENTROPY_LEN`], or if `charset` is
+/// empty. See [`try_render_password_to`] for a version of this function that
+/// returns an [`Error`] instead.
+#[track_caller]
 #[inline]
-pub fn render_password_to(entropy: &[u8], charset: CharacterSet, output: &mut [u8]) {
+pub fn render_password_to<'a>(entropy: &[u8], charset: impl Into<Alphabet<'a>>, output: &mut [u8]) {
     render_password_to_uninit(entropy, charset, slice_to_maybe_uninit_mut(output));
 }
 
+/// Same as [`render_password_to`], but returns an [`Error`] instead of
+/// panicking if its inputs are invalid.
+#[inline]
+pub fn try_render_password_to<'a>(
+    entropy: &[u8],
+    charset: impl Into<Alphabet<'a>>,
+    output: &mut [u8],
+) -> Result<(), Error> {
+    try_render_password_to_uninit(entropy, charset, slice_to_maybe_uninit_mut(output)).map(|_| ())
+}
+
 /// Same as [`render_password_to`], but directly returns the rendered password
 /// instead of requiring a mutable output buffer.
 #[cfg(feature = "std")]
+#[track_caller]
 #[inline]
-pub fn render_password(entropy: &[u8], charset: CharacterSet, len: usize) -> std::string::String {
+pub fn render_password<'a>(
+    entropy: &[u8],
+    charset: impl Into<Alphabet<'a>>,
+    len: usize,
+) -> std::string::String {
+    error::unwrap(try_render_password(entropy, charset, len))
+}
+
+/// Same as [`render_password`], but returns an [`Error`] instead of panicking
+/// if its inputs are invalid.
+#[cfg(feature = "std")]
+pub fn try_render_password<'a>(
+    entropy: &[u8],
+    charset: impl Into<Alphabet<'a>>,
+    len: usize,
+) -> Result<std::string::String, Error> {
+    let alphabet = charset.into();
+
+    // Validate arguments before allocating the output buffer.
+    check_render_args(entropy, &alphabet, len, MAX_ENTROPY_LEN, MAX_PASSWORD_LEN)?;
+
     let mut uninit_output = uninit_vec(len);
 
-    render_password_to_uninit(entropy, charset, &mut uninit_output);
+    try_render_password_to_uninit(entropy, alphabet, &mut uninit_output)?;
 
     // SAFETY: `uninit_output` was fully initialized in
-    // `render_password_to_uninit`.
+    // `try_render_password_to_uninit`.
     let output = unsafe { assume_init_vec(uninit_output) };
 
-    // SAFETY: characters are all extracted from `charset`, which only contains
-    // a limited set of ASCII characters.
-    unsafe { std::string::String::from_utf8_unchecked(output) }
+    // SAFETY: characters are all extracted from `alphabet`, which only contains
+    // ASCII characters.
+    Ok(unsafe { std::string::String::from_utf8_unchecked(output) })
+}
+
+/// Returns an [`Error`] if a password of length `len` cannot be rendered from
+/// `entropy` and `alphabet`, given the maximum entropy and password lengths.
+fn check_render_args(
+    entropy: &[u8],
+    alphabet: &Alphabet<'_>,
+    len: usize,
+    max_entropy_len: usize,
+    max_len: usize,
+) -> Result<(), Error> {
+    if entropy.is_empty() {
+        return Err(Error::EmptyEntropy);
+    }
+    if entropy.len() > max_entropy_len {
+        return Err(Error::EntropyTooLong {
+            max: max_entropy_len,
+            got: entropy.len(),
+        });
+    }
+    if alphabet.classes().is_empty() {
+        return Err(Error::EmptyCharset);
+    }
+    if let Some(index) = (0..alphabet.classes().len()).find(|&i| alphabet.class_len(i) == 0) {
+        return Err(Error::EmptyClass { index });
+    }
+
+    // At least one character of each class must fit in the password, after
+    // at least one character of the whole alphabet since characters of each
+    // class are never inserted at the end.
+    let min_len = MIN_PASSWORD_LEN.max(alphabet.classes().len() + 1);
+
+    if !(min_len..=max_len).contains(&len) {
+        return Err(Error::LengthOutOfRange {
+            min: min_len,
+            max: max_len,
+            got: len,
+        });
+    }
+
+    Ok(())
 }
 
 /// Return the SHA-256 fingerprint that corresponds to the given master password.
@@ -386,25 +933,6 @@ pub fn get_fingerprint(password: &str) -> [u8; 32] {
     unsafe { core::mem::transmute(mac.finalize().into_bytes()) }
 }
 
-/// Updates `quot` in place after dividing it by `div`, and returns the
-/// remainder.
-#[inline]
-fn div_rem(quot: &mut BigUint, div: usize) -> usize {
-    let (new_quot, rem) = quot.div_mod(div.into());
-
-    *quot = new_quot;
-
-    if cfg!(all(target_endian = "little", target_pointer_width = "64")) {
-        // We can optimize the case where the low-end of the remainder is
-        // directly equivalent to an `usize` value.
-        rem.low_u64() as usize
-    } else {
-        // We use `as_usize` below, and given that we divided by an `usize`
-        // above it is certain that `as_usize` will succeed.
-        rem.as_usize()
-    }
-}
-
 #[inline(always)]
 fn slice_to_maybe_uninit_ref<T>(slice: &[T]) -> &[MaybeUninit<T>] {
     // SAFETY: a `T` is just an initialized `MaybeUninit<T>`.
@@ -439,6 +967,71 @@ unsafe fn assume_init_vec(vec: std::vec::Vec<MaybeUninit<u8>>) -> std::vec::Vec<
     std::vec::Vec::from_raw_parts(ptr.cast(), len, capacity)
 }
 
+#[cfg(all(test, feature = "std"))]
+mod salt_tests {
+    use super::*;
+
+    #[test]
+    fn counters() {
+        let strict = |counter| {
+            let mut output = [0; 64];
+            let len = try_generate_strict_salt_to("a", "b", counter, &mut output).unwrap();
+
+            assert_eq!(output[..len], generate_strict_salt("a", "b", counter));
+
+            std::string::String::from_utf8(output[..len].to_vec()).unwrap()
+        };
+
+        assert_eq!(strict(0), "ab0");
+        assert_eq!(strict(1), "ab1");
+        assert_eq!(strict(0xffff_ffff), "abffffffff");
+        assert_eq!(strict(0x1_0000_0000), "ab100000000");
+        assert_eq!(strict(MAX_STRICT_COUNTER), "ab1fffffffffffff");
+
+        // Non-strict salts omit the counter 0, but are otherwise the same.
+        for counter in [0, 1, 0xabc, u32::MAX] {
+            let salt = generate_salt("a", "b", counter);
+
+            if counter == 0 {
+                assert_eq!(salt, b"ab");
+            } else {
+                assert_eq!(salt, strict(counter.into()).into_bytes());
+            }
+        }
+    }
+
+    #[test]
+    fn verbatim() {
+        let mut output = [0; 16];
+        let len = try_generate_strict_salt_to("", " \t", 0, &mut output).unwrap();
+
+        assert_eq!(&output[..len], b" \t0");
+        assert_eq!(generate_strict_salt("", "", 0), b"0");
+        assert_eq!(generate_strict_salt("", " ", 16), b" 10");
+    }
+
+    #[test]
+    fn errors() {
+        let mut output = [0; 8];
+
+        assert_eq!(
+            try_generate_strict_salt_to("example.org", "", 0, &mut output),
+            Err(Error::BufferTooSmall { required: 12 }),
+        );
+        assert_eq!(
+            try_generate_strict_salt_to("", "", MAX_STRICT_COUNTER + 1, &mut output),
+            Err(Error::CounterOutOfRange {
+                max: MAX_STRICT_COUNTER,
+                got: MAX_STRICT_COUNTER + 1
+            }),
+        );
+        assert_eq!(
+            generate_strict_salt_to("example.org", "", 0, &mut output),
+            Err(12)
+        );
+    }
+}
+
 #[cfg(test)]
 mod fingerprint_tests {
     use super::*;
@@ -544,4 +1137,202 @@ mod entropy_tests {
 
         assert_eq!(&entropy[..], to_bytes("fff211c16a4e776b3574c6a5c91fd252"),);
     }
+
+    #[test]
+    fn progress() {
+        let salt = generate_salt("example.org", "contact@example.org", 1);
+
+        for (algorithm, len) in [
+            (Algorithm::SHA256, 32),
+            (Algorithm::SHA512, 64),
+            (Algorithm::SHA256, 100),
+        ] {
+            for iterations in [1, 1_000, 2_500] {
+                let mut expected = std::vec![0; len];
+                let mut entropy = std::vec![0; len];
+                let mut percents = std::vec::Vec::new();
+
+                generate_extended_entropy_to(
+                    "password",
+                    &salt,
+                    algorithm,
+                    iterations,
+                    &mut expected,
+                );
+                try_generate_entropy_with_progress_to(
+                    "password",
+                    &salt,
+                    algorithm,
+                    iterations,
+                    &mut entropy,
+                    |percent| {
+                        percents.push(percent);
+                        ControlFlow::Continue(())
+                    },
+                )
+                .unwrap();
+
+                assert_eq!(entropy, expected);
+                assert_eq!(percents.first(), Some(&0));
+                assert_eq!(percents.last(), Some(&100));
+                assert!(percents.windows(2).all(|w| w[0] <= w[1]));
+            }
+        }
+
+        let mut calls = 0;
+        let result = try_generate_entropy_with_progress(
+            "password",
+            &salt,
+            Algorithm::SHA256,
+            10_000,
+            |_| {
+                calls += 1;
+
+                if calls < 3 {
+                    ControlFlow::Continue(())
+                } else {
+                    ControlFlow::Break(())
+                }
+            },
+        );
+
+        assert_eq!(result, Err(Error::Cancelled));
+        assert_eq!(calls, 3);
+
+        #[cfg(feature = "scrypt")]
+        {
+            let scrypt = Algorithm::Scrypt(ScryptParams::new(4, 1, 1));
+            let mut percents = std::vec::Vec::new();
+            let entropy =
+                try_generate_entropy_with_progress("password", &salt, scrypt, 1, |percent| {
+                    percents.push(percent);
+                    ControlFlow::Continue(())
+                });
+
+            assert_eq!(entropy, Ok(generate_entropy("password", &salt, scrypt, 1)));
+            assert_eq!(percents, [0, 100]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod error_tests {
+    use super::*;
+
+    #[test]
+    fn entropy() {
+        let mut output = [0; 32];
+
+        assert_eq!(
+            try_generate_entropy_to("", b"salt", Algorithm::SHA256, 1, &mut output),
+            Err(Error::EmptyMasterPassword),
+        );
+        assert_eq!(
+            try_generate_entropy_to("password", b"", Algorithm::SHA256, 1, &mut output),
+            Err(Error::EmptySalt),
+        );
+        assert_eq!(
+            try_generate_entropy_to("password", b"salt", Algorithm::SHA256, 0, &mut output),
+            Err(Error::ZeroIterations),
+        );
+        assert_eq!(
+            try_generate_entropy_to("password", b"salt", Algorithm::SHA256, 1, &mut []),
+            Err(Error::EntropyLengthOutOfRange {
+                min: MIN_ENTROPY_LEN,
+                max: MAX_ENTROPY_LEN,
+                got: 0
+            }),
+        );
+    }
+
+    #[test]
+    fn entropy_with_progress() {
+        let mut output = [0; 32];
+        let generate = |master_password, salt, iterations, output: &mut [u8]| {
+            try_generate_entropy_with_progress_to(
+                master_password,
+                salt,
+                Algorithm::SHA256,
+                iterations,
+                output,
+                |_| ControlFlow::Continue(()),
+            )
+        };
+
+        assert_eq!(
+            generate("", b"salt", 1, &mut output),
+            Err(Error::EmptyMasterPassword),
+        );
+        assert_eq!(
+            generate("password", b"", 1, &mut output),
+            Err(Error::EmptySalt),
+        );
+        assert_eq!(
+            generate("password", b"salt", 0, &mut output),
+            Err(Error::ZeroIterations),
+        );
+        assert_eq!(
+            generate(
+                "password",
+                b"salt",
+                1,
+                &mut [0; MAX_EXTENDED_ENTROPY_LEN + 1]
+            ),
+            Err(Error::EntropyLengthOutOfRange {
+                min: MIN_ENTROPY_LEN,
+                max: MAX_EXTENDED_ENTROPY_LEN,
+                got: MAX_EXTENDED_ENTROPY_LEN + 1,
+            }),
+        );
+    }
+
+    #[test]
+    fn render() {
+        let mut output = [0; 16];
+
+        assert_eq!(
+            try_render_password_to(&[], CharacterSet::All, &mut output),
+            Err(Error::EmptyEntropy),
+        );
+        assert_eq!(
+            try_render_password_to(&[1; 65], CharacterSet::All, &mut output),
+            Err(Error::EntropyTooLong { max: 64, got: 65 }),
+        );
+        assert_eq!(
+            try_render_password_to(&[1; 32], CharacterSet::empty(), &mut output),
+            Err(Error::EmptyCharset),
+        );
+        assert_eq!(
+            try_render_password_to(
+                &[1; 32],
+                Alphabet::new(&["abc", "01"]).unwrap().without_ambiguous(),
+                &mut output,
+            ),
+            Err(Error::EmptyClass { index: 1 }),
+        );
+        assert_eq!(
+            try_render_password_to(&[1; 32], CharacterSet::All, &mut output[..4]),
+            Err(Error::LengthOutOfRange {
+                min: MIN_PASSWORD_LEN,
+                max: MAX_PASSWORD_LEN,
+                got: 4
+            }),
+        );
+        assert_eq!(
+            try_render_password_to(
+                &[1; 32],
+                Alphabet::new(&["a", "b", "c", "d", "e", "f", "g", "h"]).unwrap(),
+                &mut output[..MAX_ALPHABET_CLASSES],
+            ),
+            Err(Error::LengthOutOfRange {
+                min: MAX_ALPHABET_CLASSES + 1,
+                max: MAX_PASSWORD_LEN,
+                got: MAX_ALPHABET_CLASSES
+            }),
+        );
+        assert_eq!(
+            try_render_password_to(&[1; 32], CharacterSet::All, &mut output),
+            Ok(()),
+        );
+    }
 }
@@ -1,7 +1,11 @@
-use clap::{CommandFactory as _, Parser};
+use clap::{CommandFactory as _, Parser, Subcommand};
 use lesspass::*;
 
 use std::io::{IsTerminal, Write};
+use std::ops::ControlFlow;
+use std::time::{Duration, Instant};
+
+use zeroize::Zeroizing;
 
 /// Generates LessPass-like passwords.
 #[derive(Parser)]
@@ -24,8 +28,37 @@ use std::io::{IsTerminal, Write};
     The two previous examples are equivalent to:
       lesspass example.org contact@example.org password -i 10000 -S
 
+    Generate a password using Argon2id with 64 MiB of memory:
+      lesspass example.org contact@example.org password --argon2id --argon2-memory 65536
+
+    Generate a 6-digit PIN:
+      lesspass example.org contact@example.org password --pin 6
+
+    Generate a passphrase of 5 capitalized words separated by spaces:
+      lesspass example.org contact@example.org password -P --words 5 --separator " " --capitalization title
+
+    Generate the passwords of all the websites listed in a file, one per line:
+      printf "example.org contact@example.org\nexample.com contact@example.org 2\n" > sites.txt
+      lesspass --batch sites.txt password
+
+    Generate the same password for all the pages of a website, like the browser extension:
+      lesspass https://www.example.org/login contact@example.org password -N
+
+    Generate a password satisfying the password rules published by a website:
+      lesspass example.org contact@example.org password --rules "required: lower; required: digit; maxlength: 12"
+
+    Recover a password created with LessPass v1:
+      lesspass example.org contact@example.org password --v1
+
+    Find the number of iterations of each algorithm taking about one second:
+      lesspass calibrate --target 1000
+
+    Show the strength of 12-character passwords without symbols:
+      lesspass strength -l 12 -S
+
 
 "#)]
+#[command(args_conflicts_with_subcommands = true, disable_help_subcommand = true)]
 pub struct Args {
     /// Target website.
     #[arg(name = "website")]
@@ -44,13 +77,28 @@ pub struct Args {
     #[arg(short = 'i', long = "iterations", default_value = "100000")]
     iterations: u32,
 
-    /// Length of the generated password.
-    #[arg(short = 'l', long = "length", default_value = "16")]
-    length: u32,
+    /// Length of the generated password [default: 16, or 12 with --v1].
+    #[arg(short = 'l', long = "length")]
+    length: Option<u32>,
 
-    /// Arbitrary number used for password generation.
+    /// Arbitrary number used for password generation. Counters greater than
+    /// 4294967295 require --strict. Without --strict, the counter 0 is not
+    /// included in the salt, so it yields different passwords than in the
+    /// official LessPass clients.
     #[arg(short = 'c', long = "counter", default_value = "1")]
-    counter: u32,
+    counter: u64,
+
+    /// Strictly follow the official LessPass clients on edge cases: the
+    /// counter 0 is included in the salt, and counters up to
+    /// 9007199254740991 are supported.
+    #[arg(long = "strict")]
+    strict: bool,
+
+    /// Generate the password with the legacy LessPass v1 algorithm (PBKDF2
+    /// with 8192 iterations and pronounceable templates), to recover
+    /// passwords created with LessPass v1.
+    #[arg(long = "v1", conflicts_with = "iterations")]
+    v1: bool,
 
     /// Use SHA-256 for password generation.
     #[arg(long = "sha256")]
@@ -64,6 +112,151 @@ pub struct Args {
     #[arg(long = "sha512")]
     sha512: bool,
 
+    /// Use Argon2id instead of PBKDF2 for password generation. The number of
+    /// iterations is ignored.
+    #[cfg(feature = "argon2")]
+    #[arg(long = "argon2id")]
+    argon2id: bool,
+
+    /// Memory size of Argon2id, in KiB.
+    #[cfg(feature = "argon2")]
+    #[arg(long = "argon2-memory", value_name = "KIB", default_value_t = Argon2Params::DEFAULT_MEMORY)]
+    argon2_memory: u32,
+
+    /// Number of passes of Argon2id over the memory.
+    #[cfg(feature = "argon2")]
+    #[arg(long = "argon2-time", value_name = "T", default_value_t = Argon2Params::DEFAULT_TIME)]
+    argon2_time: u32,
+
+    /// Degree of parallelism of Argon2id.
+    #[cfg(feature = "argon2")]
+    #[arg(long = "argon2-parallelism", value_name = "P", default_value_t = Argon2Params::DEFAULT_PARALLELISM)]
+    argon2_parallelism: u32,
+
+    /// Use scrypt instead of PBKDF2 for password generation. The number of
+    /// iterations is ignored.
+    #[cfg(feature = "scrypt")]
+    #[arg(long = "scrypt")]
+    scrypt: bool,
+
+    /// Base-2 logarithm of the cost parameter N of scrypt.
+    #[cfg(feature = "scrypt")]
+    #[arg(long = "scrypt-log-n", value_name = "LOG_N", default_value_t = ScryptParams::DEFAULT_LOG_N)]
+    scrypt_log_n: u8,
+
+    /// Block size parameter r of scrypt.
+    #[cfg(feature = "scrypt")]
+    #[arg(long = "scrypt-r", value_name = "R", default_value_t = ScryptParams::DEFAULT_R)]
+    scrypt_r: u32,
+
+    /// Parallelization parameter p of scrypt.
+    #[cfg(feature = "scrypt")]
+    #[arg(long = "scrypt-p", value_name = "P", default_value_t = ScryptParams::DEFAULT_P)]
+    scrypt_p: u32,
+
+    #[command(flatten)]
+    charset: CharsetArgs,
+
+    /// Allow passwords longer than 35 characters (up to 512), which are not
+    /// compatible with other LessPass implementations.
+    #[arg(short = 'X', long = "extended")]
+    extended: bool,
+
+    /// Generate a passphrase instead of a password. A digit and a symbol are
+    /// added to the passphrase unless excluded with --no-digits and
+    /// --no-symbols.
+    #[arg(short = 'P', long = "passphrase")]
+    passphrase: bool,
+
+    /// Number of words of the generated passphrase.
+    #[arg(long = "words", value_name = "N", default_value_t = Passphrase::DEFAULT_WORDS)]
+    words: usize,
+
+    /// Separator between the words of the generated passphrase.
+    #[arg(long = "separator", value_name = "SEP", default_value = Passphrase::DEFAULT_SEPARATOR)]
+    separator: String,
+
+    /// Capitalization of the words of the generated passphrase.
+    #[arg(
+        long = "capitalization",
+        value_name = "MODE",
+        value_parser = ["lower", "upper", "title", "random"],
+        default_value = "lower"
+    )]
+    capitalization: String,
+
+    /// File containing the words used to generate passphrases, one per line.
+    /// Only the last field of each line is used, so that diceware lists can
+    /// be used directly. Defaults to the BIP-39 English wordlist.
+    #[arg(long = "wordlist", value_name = "FILE")]
+    wordlist: Option<std::path::PathBuf>,
+
+    /// Generate a PIN of the given number of digits instead of a password.
+    /// PINs that are easy to guess (such as 1111, 1234 or 1987) are skipped.
+    #[arg(long = "pin", value_name = "LEN")]
+    pin: Option<usize>,
+
+    /// Generate the passwords of all the websites listed in the given file,
+    /// in parallel. Each line contains a website, a login and optionally a
+    /// counter separated by whitespace; passwords are printed in the same
+    /// order.
+    #[arg(long = "batch", value_name = "FILE")]
+    batch: Option<std::path::PathBuf>,
+
+    /// Generate a password satisfying the given rules, written in the
+    /// `passwordrules` format (e.g. "required: lower, upper; required: digit;
+    /// max-consecutive: 2; maxlength: 20"). The rules replace the character
+    /// options, the length is adjusted to the allowed range, and the counter is
+    /// incremented until a password satisfies the rules.
+    #[arg(long = "rules", value_name = "RULES")]
+    rules: Option<String>,
+
+    /// Normalize the website like the official LessPass clients, by removing
+    /// the scheme, port, path and leading `www.` of URLs and converting them
+    /// to lowercase.
+    #[arg(short = 'N', long = "normalize")]
+    normalize: bool,
+
+    /// Only use the registrable domain of the website (e.g. example.co.uk
+    /// for https://login.example.co.uk), according to the Public Suffix List.
+    /// Implies --normalize.
+    #[cfg(feature = "psl")]
+    #[arg(long = "registrable-domain")]
+    registrable_domain: bool,
+
+    /// Canonicalize internationalized domain names to the given form, so that
+    /// e.g. bücher.example and xn--bcher-kva.example yield the same password.
+    /// Implies --normalize.
+    #[cfg(feature = "idna")]
+    #[arg(long = "idna", value_name = "FORM", value_parser = ["unicode", "ascii"])]
+    idna: Option<String>,
+
+    /// Normalize the master password and login to the given Unicode form, so
+    /// that characters like "é" yield the same password however they were
+    /// typed. NFKC also replaces compatibility characters such as "ﬁ".
+    #[cfg(feature = "unicode-normalization")]
+    #[arg(
+        long = "normalize-unicode",
+        value_name = "FORM",
+        value_parser = ["nfc", "nfkc"]
+    )]
+    normalize_unicode: Option<String>,
+
+    /// Return the entropy instead of generating a password.
+    #[arg(short = 'E', long = "return-entropy")]
+    return_entropy: bool,
+
+    /// Print the fingerprint.
+    #[arg(short = 'F', long = "print-fingerprint")]
+    print_fingerprint: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// Options selecting the characters used in passwords.
+#[derive(clap::Args)]
+struct CharsetArgs {
     /// Exclude lowercase characters.
     #[arg(short = 'L', long = "no-lower")]
     exclude_lower: bool,
@@ -80,13 +273,44 @@ pub struct Args {
     #[arg(short = 'S', long = "no-symbols")]
     exclude_symbols: bool,
 
-    /// Return the entropy instead of generating a password.
-    #[arg(short = 'E', long = "return-entropy")]
-    return_entropy: bool,
+    /// Use the given characters as symbols instead of the default ones.
+    #[arg(long = "symbols", value_name = "CHARS")]
+    symbols: Option<String>,
 
-    /// Print the fingerprint.
-    #[arg(short = 'F', long = "print-fingerprint")]
-    print_fingerprint: bool,
+    /// Exclude characters that are easily confused with one another, such as
+    /// `l`, `1` and `I`.
+    #[arg(short = 'A', long = "no-ambiguous")]
+    exclude_ambiguous: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Recommend a number of iterations for each PBKDF2 algorithm, based on
+    /// the time it takes to derive entropy on this machine.
+    Calibrate {
+        /// Target time of a derivation, in milliseconds.
+        #[arg(long = "target", value_name = "MS", default_value_t = 500)]
+        target: u64,
+    },
+
+    /// Report the entropy of the passwords generated with the given settings,
+    /// and how long finding them by brute force would take.
+    Strength {
+        /// Length of the generated password.
+        #[arg(short = 'l', long = "length", default_value = "16")]
+        length: u32,
+
+        /// Number of iterations used for entropy generation.
+        #[arg(short = 'i', long = "iterations", default_value = "100000")]
+        iterations: u32,
+
+        #[command(flatten)]
+        charset: CharsetArgs,
+
+        /// Allow passwords longer than 35 characters (up to 512).
+        #[arg(short = 'X', long = "extended")]
+        extended: bool,
+    },
 }
 
 fn main() {
@@ -111,69 +335,390 @@ fn run() -> Result<(), &'static str> {
         iterations,
         length,
         counter,
+        strict,
+        v1,
         sha256,
         sha384,
         sha512,
-        exclude_lower,
-        exclude_upper,
-        exclude_digits,
-        exclude_symbols,
+        #[cfg(feature = "argon2")]
+        argon2id,
+        #[cfg(feature = "argon2")]
+        argon2_memory,
+        #[cfg(feature = "argon2")]
+        argon2_time,
+        #[cfg(feature = "argon2")]
+        argon2_parallelism,
+        #[cfg(feature = "scrypt")]
+        scrypt,
+        #[cfg(feature = "scrypt")]
+        scrypt_log_n,
+        #[cfg(feature = "scrypt")]
+        scrypt_r,
+        #[cfg(feature = "scrypt")]
+        scrypt_p,
+        charset:
+            CharsetArgs {
+                exclude_lower,
+                exclude_upper,
+                exclude_digits,
+                exclude_symbols,
+                symbols,
+                exclude_ambiguous,
+            },
+        extended,
+        passphrase,
+        words,
+        separator,
+        capitalization,
+        wordlist,
+        pin,
+        batch,
+        rules,
+        normalize,
+        #[cfg(feature = "psl")]
+        registrable_domain,
+        #[cfg(feature = "idna")]
+        idna,
+        #[cfg(feature = "unicode-normalization")]
+        normalize_unicode,
         return_entropy,
         print_fingerprint,
+        command,
     } = Args::parse();
 
+    match command {
+        Some(Command::Calibrate { target }) => return calibrate(Duration::from_millis(target)),
+        Some(Command::Strength {
+            length,
+            iterations,
+            charset,
+            extended,
+        }) => return strength(length, iterations, &charset, extended),
+        None => (),
+    }
+
+    // Wrap secrets as early as possible to make sure they are wiped on exit.
+    let master_password = master_password.map(MasterPassword::new);
+
+    #[cfg(feature = "unicode-normalization")]
+    let unicode_form = normalize_unicode.map(|form| match form.as_str() {
+        "nfkc" => NormalizationForm::Nfkc,
+        _ => NormalizationForm::Nfc,
+    });
+    #[cfg(not(feature = "unicode-normalization"))]
+    let unicode_form = None;
+    let login = login.map(|login| normalized_login(login, unicode_form));
+
     let mut out = std::io::stdout();
 
+    // Options of algorithms disabled at compile time are never set.
+    #[cfg(not(feature = "argon2"))]
+    let argon2id = false;
+    #[cfg(not(feature = "scrypt"))]
+    let scrypt = false;
+    #[cfg(not(feature = "psl"))]
+    let registrable_domain = false;
+
     // Validate and find digest.
-    let algorithm = match (sha256, sha384, sha512) {
-        (false, false, false) | (true, false, false) => Algorithm::SHA256,
-        (false, true, false) => Algorithm::SHA384,
-        (false, false, true) => Algorithm::SHA512,
+    let algorithm = match (sha256, sha384, sha512, argon2id, scrypt) {
+        (false, false, false, false, false) | (true, false, false, false, false) => {
+            Algorithm::SHA256
+        }
+        (false, true, false, false, false) => Algorithm::SHA384,
+        (false, false, true, false, false) => Algorithm::SHA512,
+        #[cfg(feature = "argon2")]
+        (false, false, false, true, false) => Algorithm::Argon2id(Argon2Params::new(
+            argon2_memory,
+            argon2_time,
+            argon2_parallelism,
+        )),
+        #[cfg(feature = "scrypt")]
+        (false, false, false, false, true) => {
+            Algorithm::Scrypt(ScryptParams::new(scrypt_log_n, scrypt_r, scrypt_p))
+        }
 
         _ => return Err("Only one algorithm must be provided."),
     };
 
     // Validate and find allowed characters.
-    let mut charset = CharacterSet::All;
+    let alphabet = alphabet(
+        exclude_lower,
+        exclude_upper,
+        exclude_digits,
+        exclude_symbols,
+        symbols.as_deref(),
+        exclude_ambiguous,
+    )?;
 
-    if exclude_lower {
-        charset.remove(CharacterSet::Lowercase);
-    }
-    if exclude_upper {
-        charset.remove(CharacterSet::Uppercase);
+    // Validate passphrase settings.
+    if passphrase {
+        if exclude_lower || exclude_upper || exclude_ambiguous {
+            return Err("Only digits and symbols can be excluded from passphrases.");
+        }
+        if !(MIN_PASSPHRASE_WORDS..=MAX_PASSPHRASE_WORDS).contains(&words) {
+            return Err("The number of words must be an integer in the [1; 32] range.");
+        }
     }
-    if exclude_digits {
-        charset.remove(CharacterSet::Digits);
+
+    // Validate PIN settings.
+    if let Some(pin_len) = pin {
+        if passphrase {
+            return Err("A PIN and a passphrase cannot be generated at the same time.");
+        }
+        if !(MIN_PIN_LEN..=MAX_PIN_LEN).contains(&pin_len) {
+            return Err("The PIN length must be an integer in the [4; 12] range.");
+        }
     }
-    if exclude_symbols {
-        charset.remove(CharacterSet::Symbols);
+
+    // Validate password rules.
+    let rules = match &rules {
+        Some(rules) => Some(rules.parse::<PasswordRules>().map_err(|err| match err {
+            Error::TooManyClasses { .. } => {
+                "The password rules require too many classes of characters."
+            }
+            _ => "The password rules are invalid.",
+        })?),
+        None => None,
+    };
+
+    if rules.is_some() {
+        if exclude_lower
+            || exclude_upper
+            || exclude_digits
+            || exclude_symbols
+            || symbols.is_some()
+            || exclude_ambiguous
+        {
+            return Err("Characters cannot be excluded when using password rules.");
+        }
+        if return_entropy || passphrase || pin.is_some() || batch.is_some() {
+            return Err("Password rules can only be used to generate a single password.");
+        }
     }
 
-    if charset.is_empty() {
-        return Err("Not all characters can be excluded from the generation algorithm.");
+    if v1 {
+        if sha256
+            || sha384
+            || sha512
+            || argon2id
+            || scrypt
+            || extended
+            || strict
+            || symbols.is_some()
+            || exclude_ambiguous
+        {
+            return Err("Only the length, counter and character sets can be customized with --v1.");
+        }
+        if return_entropy || passphrase || pin.is_some() || batch.is_some() || rules.is_some() {
+            return Err("LessPass v1 can only be used to generate a single password.");
+        }
     }
 
+    let website_options = WebsiteOptions {
+        normalize: normalize || registrable_domain,
+        #[cfg(feature = "psl")]
+        registrable_domain,
+        #[cfg(feature = "idna")]
+        idna: idna.map(|form| match form.as_str() {
+            "ascii" => DomainForm::Ascii,
+            _ => DomainForm::Unicode,
+        }),
+    };
+
+    let wordlist = match &wordlist {
+        Some(path) => Some(read_wordlist(path)?),
+        None => None,
+    };
+
     // Validate length / counter / iterations.
-    let length = length as usize;
+    let length = match length {
+        Some(length) => length as usize,
+        None if v1 => DEFAULT_V1_LENGTH,
+        None => Profile::DEFAULT_LENGTH,
+    };
+    let length = match &rules {
+        Some(rules) => rules.length(length),
+        None => length,
+    };
 
-    if !(MIN_PASSWORD_LEN..=MAX_PASSWORD_LEN).contains(&length) {
-        return Err("The length must be an integer in the [6; 64] range.");
+    if v1 {
+        if !(1..=MAX_V1_PASSWORD_LEN).contains(&length) {
+            return Err("The length must be an integer in the [1; 64] range.");
+        }
+    } else {
+        check_length(length, extended)?;
     }
-    if !(1..=100_000_000).contains(&iterations) {
-        return Err("The iterations must be an integer in the [1; 100,000,000] range.");
+
+    check_counter(counter, strict)?;
+    check_iterations(iterations)?;
+
+    // Generate passwords in batch mode.
+    if let Some(path) = &batch {
+        if return_entropy || print_fingerprint || passphrase || pin.is_some() {
+            return Err("Only passwords can be generated in batch mode.");
+        }
+
+        let entries = read_batch(path, counter)?
+            .into_iter()
+            .map(|(website, login, counter)| {
+                let website = website_for(&website, website_options)?;
+
+                check_counter(counter, strict)?;
+
+                Ok((website, normalized_login(login, unicode_form), counter))
+            })
+            .collect::<Result<Vec<_>, &'static str>>()?;
+        let master_password = match (website, login, master_password) {
+            (Some(pass), None, None) => MasterPassword::new(pass),
+            (None, None, None) => read_password()?, // Get password from standard input.
+            _ => return Err("Only the master password can be given in batch mode."),
+        };
+        let master_password = normalized_password(master_password, unicode_form);
+        let profiles = entries
+            .iter()
+            .map(|(website, login, counter)| {
+                Profile::new(website, login)
+                    .with_algorithm(algorithm)
+                    .with_counter(*counter)
+                    .with_length(length)
+                    .with_alphabet(alphabet)
+                    .with_iterations(iterations)
+                    .with_extended(extended)
+                    .with_strict(strict)
+            })
+            .collect::<Vec<_>>();
+
+        for password in generate_batch(&profiles, master_password.as_str()) {
+            println!("{}", password.map_err(error_message)?.as_str());
+        }
+
+        return Ok(());
+    }
+
+    // Generate a password satisfying the password rules.
+    if let Some(rules) = &rules {
+        let (website, login, master_password) = match (website, login, master_password) {
+            (Some(website), Some(login), Some(pass)) => (website, login, pass),
+            (Some(website), Some(login), None) => (website, login, read_password()?),
+            _ => return Err(""),
+        };
+        let master_password = normalized_password(master_password, unicode_form);
+        let website = website_for(&website, website_options)?;
+
+        if print_fingerprint {
+            print_buffer_hex(&algorithm.fingerprint(master_password.as_str()), &mut out)?;
+        }
+
+        let profile = Profile::new(&website, &login)
+            .with_algorithm(algorithm)
+            .with_counter(counter)
+            .with_length(length)
+            .with_alphabet(rules.alphabet())
+            .with_iterations(iterations)
+            .with_extended(extended)
+            .with_strict(strict);
+        let (password, used_counter) = profile
+            .try_generate_with_policy(master_password.as_str(), &rules.policy())
+            .map_err(|err| match err {
+                Error::PolicyNotSatisfied { .. } => {
+                    "Unable to generate a password satisfying the password rules."
+                }
+                Error::LengthOutOfRange { .. } => {
+                    "The password rules require more characters than the length allows."
+                }
+                err => error_message(err),
+            })?;
+
+        if used_counter != counter {
+            eprintln!("Counter {used_counter} was used to satisfy the password rules.");
+        }
+
+        println!("{}", password.as_str());
+
+        return Ok(());
+    }
+
+    // Generate a password with LessPass v1.
+    if v1 {
+        let (website, login, master_password) = match (website, login, master_password) {
+            (Some(website), Some(login), Some(pass)) => (website, login, pass),
+            (Some(website), Some(login), None) => (website, login, read_password()?),
+            _ => return Err(""),
+        };
+        let master_password = normalized_password(master_password, unicode_form);
+        let website = website_for(&website, website_options)?;
+        let template = charset(
+            exclude_lower,
+            exclude_upper,
+            exclude_digits,
+            exclude_symbols,
+        )?
+        .get_v1_template();
+
+        if print_fingerprint {
+            print_buffer_hex(&get_fingerprint(master_password.as_str()), &mut out)?;
+        }
+
+        let password = try_generate_v1_password(
+            &website,
+            &login,
+            master_password.as_str(),
+            // The counter was checked to fit in a `u32` above.
+            counter as u32,
+            template,
+            length,
+        )
+        .map_err(error_message)?;
+
+        println!("{}", password.as_str());
+
+        return Ok(());
     }
 
+    // Build passphrase settings, which determine the length of the entropy.
+    let wordlist = wordlist
+        .as_ref()
+        .map(|words| words.iter().map(String::as_str).collect::<Vec<_>>());
+    let passphrase = passphrase.then(|| {
+        let symbols = match &symbols {
+            _ if exclude_symbols => "",
+            Some(symbols) => symbols,
+            None => CharacterSet::SYMBOLS,
+        };
+        let capitalization = match capitalization.as_str() {
+            "upper" => Capitalization::Uppercase,
+            "title" => Capitalization::Title,
+            "random" => Capitalization::Random,
+            _ => Capitalization::Lowercase,
+        };
+        let settings = Passphrase::new()
+            .with_words(words)
+            .with_separator(&separator)
+            .with_capitalization(capitalization)
+            .with_digit(!exclude_digits)
+            .with_symbols(symbols);
+
+        match &wordlist {
+            Some(wordlist) => settings.with_wordlist(wordlist),
+            None => settings,
+        }
+    });
+
     // Compute entropy.
     let entropy = match (website, login, master_password) {
         (pass, None, None) => {
+            // The first argument is the master password or entropy, not a website.
+            let pass = pass.map(MasterPassword::new);
+
             if print_fingerprint {
                 // Only the password was given, so we return its fingerprint.
                 let master_password = match pass {
                     Some(pass) => pass,
                     None => read_password()?, // Get password from standard input.
                 };
+                let master_password = normalized_password(master_password, unicode_form);
 
-                print_buffer_hex(get_fingerprint(&master_password).as_ref(), &mut out)?;
+                print_buffer_hex(&algorithm.fingerprint(master_password.as_str()), &mut out)?;
 
                 return Ok(());
             }
@@ -194,7 +739,7 @@ fn run() -> Result<(), &'static str> {
 
             // If the password matches the format of the entropy, then we use it. Otherwise
             // we only return the fingerprint.
-            match parse_entropy(&entropy) {
+            match parse_entropy(entropy.as_str()) {
                 Some(entropy) => {
                     // The entropy was given to us, so we use it.
                     entropy
@@ -208,14 +753,44 @@ fn run() -> Result<(), &'static str> {
                 Some(pass) => pass,
                 None => read_password()?, // Get password from standard input.
             };
+            let master_password = normalized_password(master_password, unicode_form);
 
-            let salt = generate_salt(&website, &login, counter);
+            let website = website_for(&website, website_options)?;
+            let salt = Zeroizing::new(if strict {
+                generate_strict_salt(&website, &login, counter)
+            } else {
+                // The counter was checked to fit in a `u32` above.
+                generate_salt(&website, &login, counter as u32)
+            });
 
             if print_fingerprint {
-                print_buffer_hex(get_fingerprint(&master_password).as_ref(), &mut out)?;
+                print_buffer_hex(&algorithm.fingerprint(master_password.as_str()), &mut out)?;
             }
 
-            generate_entropy(&master_password, &salt, algorithm, iterations)
+            let entropy_len = if let Some(settings) = &passphrase {
+                algorithm
+                    .entropy_len()
+                    .max(passphrase_entropy_len(settings))
+            } else if length > MAX_PASSWORD_LEN {
+                algorithm
+                    .entropy_len()
+                    .max(extended_entropy_len(alphabet, length))
+            } else {
+                algorithm.entropy_len()
+            };
+            let mut entropy = Zeroizing::new(vec![0; entropy_len]);
+
+            try_generate_entropy_with_progress_to(
+                master_password.as_str(),
+                &salt,
+                algorithm,
+                iterations,
+                &mut entropy,
+                progress_indicator(),
+            )
+            .map_err(error_message)?;
+
+            Entropy::from_slice(&entropy).map_err(error_message)?
         }
         _ => {
             // We cannot do anything with what we were given; return an error.
@@ -225,11 +800,29 @@ fn run() -> Result<(), &'static str> {
 
     // Compute and print password.
     if return_entropy {
-        print_buffer_hex(&entropy, &mut out)?;
+        print_buffer_hex(entropy.as_bytes(), &mut out)?;
+    } else if let Some(pin_len) = pin {
+        let pin = try_render_pin(entropy.as_bytes(), pin_len);
+        let pin = Password::new(pin.map_err(error_message)?);
+
+        println!("{}", pin.as_str());
+    } else if let Some(settings) = &passphrase {
+        let passphrase = try_render_passphrase(entropy.as_bytes(), settings);
+        let passphrase = Password::new(passphrase.map_err(|err| match err {
+            Error::EntropyExhausted => "The entropy is too short to generate this passphrase.",
+            err => error_message(err),
+        })?);
+
+        println!("{}", passphrase.as_str());
     } else {
-        let password = render_password(&entropy, charset, length);
+        let password = if extended {
+            try_render_extended_password(entropy.as_bytes(), alphabet, length)
+        } else {
+            try_render_password(entropy.as_bytes(), alphabet, length)
+        };
+        let password = Password::new(password.map_err(error_message)?);
 
-        println!("{}", password);
+        println!("{}", password.as_str());
     }
 
     Ok(())
@@ -246,11 +839,383 @@ fn print_buffer_hex(buf: &[u8], out: &mut dyn Write) -> Result<(), &'static str>
     Ok(())
 }
 
-fn read_password() -> Result<String, &'static str> {
+/// Returns the alphabet of passwords, given the characters to exclude.
+fn alphabet(
+    exclude_lower: bool,
+    exclude_upper: bool,
+    exclude_digits: bool,
+    exclude_symbols: bool,
+    symbols: Option<&str>,
+    exclude_ambiguous: bool,
+) -> Result<Alphabet<'_>, &'static str> {
+    let charset = charset(
+        exclude_lower,
+        exclude_upper,
+        exclude_digits,
+        exclude_symbols,
+    )?;
+    let mut alphabet = match symbols {
+        None => Alphabet::from_charset(charset),
+        Some(_) if exclude_symbols => {
+            return Err("Custom symbols cannot be used if symbols are excluded.");
+        }
+        Some(symbols) => {
+            let (mut sets, sets_len) = charset.get_sets();
+
+            // Symbols are always the last set.
+            sets[sets_len - 1] = symbols;
+
+            Alphabet::new(&sets[..sets_len]).map_err(|err| match err {
+                Error::DuplicateCharacter { .. } => {
+                    "Custom symbols must not contain duplicates or characters of other classes."
+                }
+                _ => "Custom symbols must be printable ASCII characters.",
+            })?
+        }
+    };
+
+    if exclude_ambiguous {
+        alphabet = alphabet.without_ambiguous();
+    }
+
+    Ok(alphabet)
+}
+
+/// Returns the character set of passwords, given the characters to exclude.
+fn charset(
+    exclude_lower: bool,
+    exclude_upper: bool,
+    exclude_digits: bool,
+    exclude_symbols: bool,
+) -> Result<CharacterSet, &'static str> {
+    let mut charset = CharacterSet::All;
+
+    if exclude_lower {
+        charset.remove(CharacterSet::Lowercase);
+    }
+    if exclude_upper {
+        charset.remove(CharacterSet::Uppercase);
+    }
+    if exclude_digits {
+        charset.remove(CharacterSet::Digits);
+    }
+    if exclude_symbols {
+        charset.remove(CharacterSet::Symbols);
+    }
+
+    if charset.is_empty() {
+        return Err("Not all characters can be excluded from the generation algorithm.");
+    }
+
+    Ok(charset)
+}
+
+/// How websites are normalized before generating passwords.
+#[derive(Clone, Copy)]
+struct WebsiteOptions {
+    normalize: bool,
+    #[cfg(feature = "psl")]
+    registrable_domain: bool,
+    #[cfg(feature = "idna")]
+    idna: Option<DomainForm>,
+}
+
+/// Returns the website used to generate passwords, normalized if requested.
+fn website_for(website: &str, options: WebsiteOptions) -> Result<String, &'static str> {
+    #[cfg(feature = "idna")]
+    let normalize = options.normalize || options.idna.is_some();
+    #[cfg(not(feature = "idna"))]
+    let normalize = options.normalize;
+
+    if !normalize {
+        return Ok(website.to_owned());
+    }
+
+    #[cfg_attr(not(any(feature = "psl", feature = "idna")), allow(unused_mut))]
+    let mut host = normalize_website(website);
+
+    // Suffixes of internationalized domain names are listed in Unicode form.
+    #[cfg(feature = "idna")]
+    if options.idna.is_some() {
+        host = canonicalize_domain(&host, DomainForm::Unicode).map_err(error_message)?;
+    }
+
+    #[cfg(feature = "psl")]
+    if options.registrable_domain {
+        host = registrable_domain(&host).to_owned();
+    }
+
+    #[cfg(feature = "idna")]
+    if options.idna == Some(DomainForm::Ascii) {
+        host = canonicalize_domain(&host, DomainForm::Ascii).map_err(error_message)?;
+    }
+
+    Ok(host)
+}
+
+/// Returns the master password in the given Unicode normalization form, if any.
+fn normalized_password(
+    master_password: MasterPassword,
+    form: Option<NormalizationForm>,
+) -> MasterPassword {
+    match form {
+        #[cfg(feature = "unicode-normalization")]
+        Some(form) => master_password.normalized(form),
+        _ => master_password,
+    }
+}
+
+/// Returns the login in the given Unicode normalization form, if any.
+fn normalized_login(login: String, form: Option<NormalizationForm>) -> String {
+    match form {
+        #[cfg(feature = "unicode-normalization")]
+        Some(form) => normalize_unicode(&login, form),
+        _ => login,
+    }
+}
+
+/// Stand-in for [`NormalizationForm`] when Unicode normalization is disabled,
+/// in which case no form can be selected.
+#[cfg(not(feature = "unicode-normalization"))]
+#[derive(Clone, Copy)]
+enum NormalizationForm {}
+
+fn check_length(length: usize, extended: bool) -> Result<(), &'static str> {
+    if extended {
+        if !(MIN_PASSWORD_LEN..=MAX_EXTENDED_PASSWORD_LEN).contains(&length) {
+            return Err("The length must be an integer in the [5; 512] range.");
+        }
+    } else if !(MIN_PASSWORD_LEN..=MAX_PASSWORD_LEN).contains(&length) {
+        return Err("The length must be an integer in the [5; 35] range.");
+    }
+
+    Ok(())
+}
+
+fn check_counter(counter: u64, strict: bool) -> Result<(), &'static str> {
+    if strict {
+        if counter > MAX_STRICT_COUNTER {
+            return Err("The counter must be an integer in the [0; 9007199254740991] range.");
+        }
+    } else if u32::try_from(counter).is_err() {
+        return Err(
+            "The counter must be an integer in the [0; 4294967295] range; use --strict for larger counters.",
+        );
+    }
+
+    Ok(())
+}
+
+fn check_iterations(iterations: u32) -> Result<(), &'static str> {
+    if !(1..=100_000_000).contains(&iterations) {
+        return Err("The iterations must be an integer in the [1; 100,000,000] range.");
+    }
+
+    Ok(())
+}
+
+/// Prints the number of iterations recommended for each PBKDF2 algorithm.
+fn calibrate(target: Duration) -> Result<(), &'static str> {
+    let algorithms = [
+        ("sha256", Algorithm::SHA256),
+        ("sha384", Algorithm::SHA384),
+        ("sha512", Algorithm::SHA512),
+    ];
+
+    println!(
+        "Iterations for a derivation time of {} ms:",
+        target.as_millis()
+    );
+    println!();
+    println!("    {:<10} {:>12}", "ALGORITHM", "ITERATIONS");
+
+    for (name, algorithm) in algorithms {
+        let iterations = try_calibrate_iterations(algorithm, target).map_err(error_message)?;
+
+        println!("    {name:<10} {iterations:>12}");
+    }
+
+    println!();
+    println!("Use them with `--iterations`, along with the corresponding algorithm.");
+
+    Ok(())
+}
+
+/// Prints the strength of the passwords generated with the given settings.
+fn strength(
+    length: u32,
+    iterations: u32,
+    charset: &CharsetArgs,
+    extended: bool,
+) -> Result<(), &'static str> {
+    // Number of guesses (or of PBKDF2 iterations) per second assumed for an
+    // attacker with dedicated hardware.
+    const GUESSES_PER_SECOND: f64 = 1e10;
+
+    let alphabet = alphabet(
+        charset.exclude_lower,
+        charset.exclude_upper,
+        charset.exclude_digits,
+        charset.exclude_symbols,
+        charset.symbols.as_deref(),
+        charset.exclude_ambiguous,
+    )?;
+    let length = length as usize;
+
+    check_length(length, extended)?;
+    check_iterations(iterations)?;
+
+    let strength = try_password_strength(alphabet, length).map_err(error_message)?;
+
+    println!(
+        "Passwords of {length} characters, picked out of {} characters:",
+        alphabet.len(),
+    );
+    println!();
+    println!("    Entropy: {:.1} bits", strength.bits());
+    println!(
+        "    Average time to find a password by brute force, at 10 billion guesses per second:"
+    );
+    println!(
+        "        {:<32} {}",
+        "stored with a fast hash:",
+        format_time(strength.crack_time(GUESSES_PER_SECOND)),
+    );
+    println!(
+        "        {:<32} {}",
+        format!("derived with {iterations} iterations:"),
+        format_time(strength.crack_time(GUESSES_PER_SECOND / f64::from(iterations))),
+    );
+
+    Ok(())
+}
+
+/// Formats a duration given in seconds in a human-readable way.
+fn format_time(seconds: f64) -> String {
+    const UNITS: [(&str, f64); 5] = [
+        ("years", 365.25 * 24.0 * 3600.0),
+        ("days", 24.0 * 3600.0),
+        ("hours", 3600.0),
+        ("minutes", 60.0),
+        ("seconds", 1.0),
+    ];
+
+    if seconds < 1.0 {
+        return "less than a second".to_owned();
+    }
+
+    let (unit, unit_seconds) = UNITS
+        .into_iter()
+        .find(|&(_, unit_seconds)| seconds >= unit_seconds)
+        .unwrap_or(UNITS[UNITS.len() - 1]);
+    let value = seconds / unit_seconds;
+
+    if value < 1e6 {
+        format!("{value:.0} {unit}")
+    } else {
+        format!("{value:.1e} {unit}")
+    }
+}
+
+/// Returns a progress callback which shows the progress of the derivation
+/// on stderr if it is a terminal, once the derivation has been running for a
+/// while.
+fn progress_indicator() -> impl FnMut(u8) -> ControlFlow<()> {
+    const DELAY: Duration = Duration::from_millis(500);
+    const MESSAGE: &str = "Deriving the entropy...";
+
+    let start = Instant::now();
+    let enabled = std::io::stderr().is_terminal();
+    let mut shown = None;
+
+    move |percent| {
+        if enabled && shown != Some(percent) && start.elapsed() >= DELAY {
+            eprint!("\r{MESSAGE} {percent:>3}%");
+            shown = Some(percent);
+        }
+        if percent == 100 && shown.is_some() {
+            // Clear the line before the password is printed.
+            eprint!("\r{:width$}\r", "", width = MESSAGE.len() + 5);
+        }
+
+        ControlFlow::Continue(())
+    }
+}
+
+fn error_message(err: Error) -> &'static str {
+    match err {
+        Error::EmptyMasterPassword => "The master password must not be empty.",
+        Error::EmptySalt => "The website and login must not both be empty.",
+        Error::SaltTooShort { .. } => {
+            "The website and login must be at least 8 characters long when using Argon2id."
+        }
+        Error::InvalidKdfParams => "The parameters of Argon2id or scrypt are invalid.",
+        Error::EntropyTooLong { .. } => "The entropy is too long; try using --extended.",
+        Error::EmptyClass { .. } => "Custom symbols must not all be ambiguous characters.",
+        Error::InvalidSymbols => "Custom symbols must be printable ASCII characters.",
+        Error::EntropyExhausted => "Unable to generate a PIN that is not weak.",
+        Error::InvalidDomain => "The website is not a valid domain name.",
+        Error::EmptyLogin => "The login must not be empty with --v1.",
+        _ => "Unable to generate the password.",
+    }
+}
+
+fn read_wordlist(path: &std::path::Path) -> Result<Vec<String>, &'static str> {
+    let contents = std::fs::read_to_string(path).map_err(|_| "Unable to read the wordlist.")?;
+    let words = contents
+        .lines()
+        .filter_map(|line| line.split_whitespace().last())
+        .map(str::to_owned)
+        .collect::<Vec<_>>();
+
+    if words.is_empty() {
+        return Err("The wordlist must contain at least one word.");
+    }
+
+    Ok(words)
+}
+
+fn read_batch(
+    path: &std::path::Path,
+    default_counter: u64,
+) -> Result<Vec<(String, String, u64)>, &'static str> {
+    let contents = std::fs::read_to_string(path).map_err(|_| "Unable to read the batch file.")?;
+    let mut entries = Vec::new();
+
+    for line in contents.lines() {
+        let mut fields = line.split_whitespace();
+
+        let (website, login) = match (fields.next(), fields.next()) {
+            (None, _) => continue,
+            (Some(website), Some(login)) => (website.to_owned(), login.to_owned()),
+            (Some(_), None) => {
+                return Err("Each line of the batch file must contain a website and a login.")
+            }
+        };
+        let counter = match fields.next() {
+            Some(counter) => counter
+                .parse()
+                .map_err(|_| "Counters in the batch file must be positive integers.")?,
+            None => default_counter,
+        };
+
+        if fields.next().is_some() {
+            return Err("Each line of the batch file must contain at most a website, a login and a counter.");
+        }
+
+        entries.push((website, login, counter));
+    }
+
+    Ok(entries)
+}
+
+fn read_password() -> Result<MasterPassword, &'static str> {
     // If the input is passed from Stdin, it fails on my machine,
     // so we handle this here
     if std::io::stdin().is_terminal() {
-        rpassword::read_password().map_err(|_| "Unable to read password or entropy.")
+        rpassword::read_password()
+            .map(MasterPassword::new)
+            .map_err(|_| "Unable to read password or entropy.")
     } else {
         let stdin = std::io::stdin();
         let mut input = String::new();
@@ -266,21 +1231,21 @@ fn read_password() -> Result<String, &'static str> {
             input.truncate(new_len);
         }
 
-        Ok(input)
+        Ok(MasterPassword::new(input))
     }
 }
 
-fn parse_entropy(entropy: &str) -> Option<Vec<u8>> {
-    if (entropy.len() & 1) == 1 {
+fn parse_entropy(entropy: &str) -> Option<Entropy> {
+    if (entropy.len() & 1) == 1 || entropy.len() > MAX_EXTENDED_ENTROPY_LEN * 2 {
         return None;
     }
 
     let len = entropy.len() / 2;
-    let mut result = Vec::with_capacity(len);
+    let mut result = Zeroizing::new([0; MAX_EXTENDED_ENTROPY_LEN]);
 
     for i in 0..len {
-        result.push(u8::from_str_radix(&entropy[i * 2..i * 2 + 2], 16).ok()?);
+        result[i] = u8::from_str_radix(entropy.get(i * 2..i * 2 + 2)?, 16).ok()?;
     }
 
-    Some(result)
+    Entropy::from_slice(&result[..len]).ok()
 }
@@ -0,0 +1,550 @@
+//! Master passwords whose HMAC key schedule is computed once, and then reused
+//! to derive the entropy of many websites.
+
+use core::ops::ControlFlow;
+
+use pbkdf2::hmac::digest::{FixedOutput, KeyInit, Update};
+use pbkdf2::hmac::Hmac;
+use sha2::{Sha256, Sha384, Sha512};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::lanes::{self, HmacStates};
+use crate::{
+    check_entropy_args, error, Algorithm, Error, MAX_ENTROPY_LEN, MAX_EXTENDED_ENTROPY_LEN,
+    MIN_ENTROPY_LEN,
+};
+
+/// A master password prepared to derive entropy with PBKDF2.
+///
+/// Deriving entropy with [`generate_entropy_to`](crate::generate_entropy_to)
+/// computes the HMAC key schedule of the master password for every website.
+/// A `MasterKey` computes it (and the fingerprint of the master password)
+/// once, which is faster when generating many passwords with the same master
+/// password:
+///
+/// ```
+/// use lesspass::{generate_salt, render_password_to, Algorithm, CharacterSet, MasterKey};
+///
+/// let key = MasterKey::new("password", Algorithm::SHA256);
+/// let mut entropy = [0; 32];
+/// let mut password = [0; 16];
+///
+/// for website in ["example.org", "example.com"] {
+///     let salt = generate_salt(website, "contact@example.org", 1);
+///
+///     key.generate_entropy_to(&salt, 1_000, &mut entropy);
+///     render_password_to(&entropy, CharacterSet::All, &mut password);
+/// }
+/// # let mut expected = [0; 32];
+/// # let salt = generate_salt("example.com", "contact@example.org", 1);
+/// # lesspass::generate_entropy_to("password", &salt, Algorithm::SHA256, 1_000, &mut expected);
+/// # assert_eq!(entropy, expected);
+/// ```
+///
+/// Only PBKDF2 is supported, since memory-hard functions cannot be
+/// precomputed this way. The key schedule is as sensitive as the master
+/// password itself, and is zeroed out when dropped.
+pub struct MasterKey {
+    mac: KeyedMac,
+    fingerprint: [u8; 32],
+}
+
+/// The HMAC state of a master password, after its key schedule was computed.
+///
+/// With SHA-256, the states used to compute several derivations at once are
+/// also stored.
+enum KeyedMac {
+    Sha256(Hmac<Sha256>, HmacStates),
+    Sha384(Hmac<Sha384>),
+    Sha512(Hmac<Sha512>),
+}
+
+impl MasterKey {
+    /// Computes the key schedule of the given master password, to be used
+    /// with PBKDF2 and the given algorithm.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `master_password` is empty, or if `algorithm` does not use
+    /// PBKDF2. See [`MasterKey::try_new`] for a version of this function that
+    /// returns an [`Error`] instead.
+    #[track_caller]
+    #[inline]
+    pub fn new(master_password: &str, algorithm: Algorithm) -> Self {
+        error::unwrap(Self::try_new(master_password, algorithm))
+    }
+
+    /// Same as [`MasterKey::new`], but returns an [`Error`] instead of
+    /// panicking if its inputs are invalid.
+    pub fn try_new(master_password: &str, algorithm: Algorithm) -> Result<Self, Error> {
+        if master_password.is_empty() {
+            return Err(Error::EmptyMasterPassword);
+        }
+
+        let key = master_password.as_bytes();
+        let fingerprint_mac = Hmac::<Sha256>::new_from_slice(key)
+            .expect("Hmac's new_from_slice implementation is infallible");
+        let mac = match algorithm {
+            Algorithm::SHA256 => KeyedMac::Sha256(fingerprint_mac.clone(), HmacStates::new(key)),
+            Algorithm::SHA384 => KeyedMac::Sha384(keyed_mac(key)),
+            Algorithm::SHA512 => KeyedMac::Sha512(keyed_mac(key)),
+            #[allow(unreachable_patterns)]
+            _ => return Err(Error::UnsupportedAlgorithm),
+        };
+
+        // Same as `get_fingerprint`, which hashes an empty message.
+        let mut fingerprint = [0; 32];
+
+        finalize_into(fingerprint_mac, &mut fingerprint);
+
+        Ok(Self { mac, fingerprint })
+    }
+
+    /// Returns the algorithm used with PBKDF2.
+    pub const fn algorithm(&self) -> Algorithm {
+        match self.mac {
+            KeyedMac::Sha256(..) => Algorithm::SHA256,
+            KeyedMac::Sha384(_) => Algorithm::SHA384,
+            KeyedMac::Sha512(_) => Algorithm::SHA512,
+        }
+    }
+
+    /// Returns the fingerprint of the master password, which is the same as
+    /// the one returned by [`get_fingerprint`](crate::get_fingerprint).
+    #[inline]
+    pub const fn fingerprint(&self) -> [u8; 32] {
+        self.fingerprint
+    }
+
+    /// Same as [`generate_entropy_to`](crate::generate_entropy_to), but uses
+    /// this master key and its algorithm.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `salt` is empty, if `iterations` is 0, or if `output` is empty
+    /// or longer than [`MAX_ENTROPY_LEN`].
+    #[track_caller]
+    #[inline]
+    pub fn generate_entropy_to(&self, salt: &[u8], iterations: u32, output: &mut [u8]) {
+        error::unwrap(self.try_generate_entropy_to(salt, iterations, output))
+    }
+
+    /// Same as [`MasterKey::generate_entropy_to`], but returns an [`Error`]
+    /// instead of panicking if its inputs are invalid.
+    #[inline]
+    pub fn try_generate_entropy_to(
+        &self,
+        salt: &[u8],
+        iterations: u32,
+        output: &mut [u8],
+    ) -> Result<(), Error> {
+        self.derive(salt, iterations, output, MAX_ENTROPY_LEN)
+    }
+
+    /// Same as [`MasterKey::generate_entropy_to`], but directly returns the
+    /// entropy buffer instead of requiring a mutable output buffer.
+    ///
+    /// The length of the resulting buffer is given by
+    /// [`Algorithm::entropy_len`].
+    #[cfg(feature = "std")]
+    #[track_caller]
+    #[inline]
+    pub fn generate_entropy(&self, salt: &[u8], iterations: u32) -> std::vec::Vec<u8> {
+        error::unwrap(self.try_generate_entropy(salt, iterations))
+    }
+
+    /// Same as [`MasterKey::generate_entropy`], but returns an [`Error`]
+    /// instead of panicking if its inputs are invalid.
+    #[cfg(feature = "std")]
+    pub fn try_generate_entropy(
+        &self,
+        salt: &[u8],
+        iterations: u32,
+    ) -> Result<std::vec::Vec<u8>, Error> {
+        let mut output = std::vec![0; self.algorithm().entropy_len()];
+
+        self.try_generate_entropy_to(salt, iterations, &mut output)?;
+
+        Ok(output)
+    }
+
+    /// Same as [`MasterKey::generate_entropy_to`], but allows `output` to be up
+    /// to [`MAX_EXTENDED_ENTROPY_LEN`] bytes long.
+    ///
+    /// # Panics
+    ///
+    /// Panics in the same cases as [`MasterKey::generate_entropy_to`], with
+    /// [`MAX_EXTENDED_ENTROPY_LEN`] as maximum length.
+    #[track_caller]
+    #[inline]
+    pub fn generate_extended_entropy_to(&self, salt: &[u8], iterations: u32, output: &mut [u8]) {
+        error::unwrap(self.try_generate_extended_entropy_to(salt, iterations, output))
+    }
+
+    /// Same as [`MasterKey::generate_extended_entropy_to`], but returns an
+    /// [`Error`] instead of panicking if its inputs are invalid.
+    #[inline]
+    pub fn try_generate_extended_entropy_to(
+        &self,
+        salt: &[u8],
+        iterations: u32,
+        output: &mut [u8],
+    ) -> Result<(), Error> {
+        self.derive(salt, iterations, output, MAX_EXTENDED_ENTROPY_LEN)
+    }
+
+    /// Same as [`MasterKey::generate_extended_entropy_to`], but derives the
+    /// entropy of each salt of `salts` to the corresponding buffer of
+    /// `outputs`.
+    ///
+    /// With SHA-256, several derivations are computed at once using SIMD
+    /// instructions (4 or 8 at a time on x86-64, depending on whether AVX2 is
+    /// available), which is faster than deriving the entropy of each salt
+    /// separately, especially on CPUs without dedicated SHA instructions.
+    /// Results are the same in both cases.
+    ///
+    /// ```
+    /// use lesspass::{generate_entropy, generate_salt, Algorithm, MasterKey};
+    ///
+    /// let key = MasterKey::new("password", Algorithm::SHA256);
+    /// let salts = ["example.org", "example.com", "example.net"]
+    ///     .map(|website| generate_salt(website, "contact@example.org", 1));
+    /// let mut entropies = [[0; 32]; 3];
+    ///
+    /// key.generate_entropy_batch_to(&salts, 1_000, &mut entropies);
+    ///
+    /// for (salt, entropy) in salts.iter().zip(entropies) {
+    ///     assert_eq!(entropy.to_vec(), generate_entropy("password", salt, Algorithm::SHA256, 1_000));
+    /// }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `salts` and `outputs` have different lengths, or in the same
+    /// cases as [`MasterKey::generate_extended_entropy_to`] for any salt and
+    /// output.
+    #[track_caller]
+    #[inline]
+    pub fn generate_entropy_batch_to<S: AsRef<[u8]>, O: AsMut<[u8]>>(
+        &self,
+        salts: &[S],
+        iterations: u32,
+        outputs: &mut [O],
+    ) {
+        error::unwrap(self.try_generate_entropy_batch_to(salts, iterations, outputs))
+    }
+
+    /// Same as [`MasterKey::generate_entropy_batch_to`], but returns an
+    /// [`Error`] instead of panicking if its inputs are invalid, in which
+    /// case no entropy is derived.
+    pub fn try_generate_entropy_batch_to<S: AsRef<[u8]>, O: AsMut<[u8]>>(
+        &self,
+        salts: &[S],
+        iterations: u32,
+        outputs: &mut [O],
+    ) -> Result<(), Error> {
+        if salts.len() != outputs.len() {
+            return Err(Error::OutputCountMismatch {
+                expected: salts.len(),
+                got: outputs.len(),
+            });
+        }
+
+        for (salt, output) in salts.iter().zip(outputs.iter_mut()) {
+            check_entropy_args(
+                salt.as_ref(),
+                output.as_mut().len(),
+                MAX_EXTENDED_ENTROPY_LEN,
+            )?;
+        }
+
+        if iterations == 0 {
+            return Err(Error::ZeroIterations);
+        }
+
+        match &self.mac {
+            KeyedMac::Sha256(mac, states) => lanes::pbkdf2(mac, states, salts, iterations, outputs),
+            _ => {
+                for (salt, output) in salts.iter().zip(outputs) {
+                    self.derive(
+                        salt.as_ref(),
+                        iterations,
+                        output.as_mut(),
+                        MAX_EXTENDED_ENTROPY_LEN,
+                    )?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Derives entropy from the given salt, writing at most `max_len` bytes.
+    fn derive(
+        &self,
+        salt: &[u8],
+        iterations: u32,
+        output: &mut [u8],
+        max_len: usize,
+    ) -> Result<(), Error> {
+        check_entropy_args(salt, output.len(), max_len)?;
+
+        if iterations == 0 {
+            return Err(Error::ZeroIterations);
+        }
+
+        let flow = self.pbkdf2(salt, iterations, output, &mut |_| ControlFlow::Continue(()));
+
+        debug_assert!(flow.is_continue());
+
+        Ok(())
+    }
+
+    /// Derives entropy from the given salt, calling `progress` with the
+    /// percentage of completed work regularly. Inputs must be valid.
+    ///
+    /// Returns [`Error::Cancelled`] if `progress` returns
+    /// [`ControlFlow::Break`].
+    pub(crate) fn derive_with_progress(
+        &self,
+        salt: &[u8],
+        iterations: u32,
+        output: &mut [u8],
+        progress: &mut dyn FnMut(u8) -> ControlFlow<()>,
+    ) -> Result<(), Error> {
+        debug_assert!(check_entropy_args(salt, output.len(), MAX_EXTENDED_ENTROPY_LEN).is_ok());
+        debug_assert_ne!(iterations, 0);
+
+        let block_len = self.algorithm().entropy_len();
+        let blocks = (output.len() + block_len - 1) / block_len;
+        let total = blocks as u64 * u64::from(iterations);
+        let mut done = 0;
+
+        if progress(0).is_break() {
+            return Err(Error::Cancelled);
+        }
+
+        let flow = self.pbkdf2(salt, iterations, output, &mut |work| {
+            done += u64::from(work);
+
+            progress((done * 100 / total) as u8)
+        });
+
+        match flow {
+            ControlFlow::Continue(()) => Ok(()),
+            ControlFlow::Break(()) => Err(Error::Cancelled),
+        }
+    }
+
+    /// Runs PBKDF2 with the keyed HMAC state of the master password. See
+    /// [`pbkdf2`].
+    #[inline]
+    fn pbkdf2(
+        &self,
+        salt: &[u8],
+        iterations: u32,
+        output: &mut [u8],
+        progress: &mut dyn FnMut(u32) -> ControlFlow<()>,
+    ) -> ControlFlow<()> {
+        match &self.mac {
+            KeyedMac::Sha256(mac, _) => pbkdf2(mac, salt, iterations, output, progress),
+            KeyedMac::Sha384(mac) => pbkdf2(mac, salt, iterations, output, progress),
+            KeyedMac::Sha512(mac) => pbkdf2(mac, salt, iterations, output, progress),
+        }
+    }
+}
+
+impl Drop for MasterKey {
+    fn drop(&mut self) {
+        // SAFETY: HMAC states only hold integers and byte arrays (for which
+        // zero is a valid value), and do not implement `Drop`. The enum
+        // discriminant is kept as-is.
+        unsafe {
+            match &mut self.mac {
+                KeyedMac::Sha256(mac, _) => zeroize::zeroize_flat_type(mac),
+                KeyedMac::Sha384(mac) => zeroize::zeroize_flat_type(mac),
+                KeyedMac::Sha512(mac) => zeroize::zeroize_flat_type(mac),
+            }
+        }
+
+        self.fingerprint.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for MasterKey {}
+
+/// Returns the HMAC state keyed with `key`.
+#[inline]
+fn keyed_mac<M: KeyInit>(key: &[u8]) -> M {
+    M::new_from_slice(key).expect("Hmac's new_from_slice implementation is infallible")
+}
+
+/// Finalizes `mac`, writing the first `output.len()` bytes of its result to
+/// `output`.
+#[inline]
+fn finalize_into<M: FixedOutput>(mac: M, output: &mut [u8]) {
+    let mut result = mac.finalize_fixed();
+
+    output.copy_from_slice(&result[..output.len()]);
+    result.as_mut_slice().zeroize();
+}
+
+/// The number of iterations of PBKDF2 computed between two calls to the
+/// progress callback of [`pbkdf2`].
+const PROGRESS_INTERVAL: u32 = 1_000;
+
+/// Runs PBKDF2 using the given keyed HMAC state, which is cloned instead of
+/// being computed from the password for each block and iteration.
+///
+/// `progress` is called with the number of iterations computed since its
+/// previous call, at least every [`PROGRESS_INTERVAL`] iterations and at the
+/// end of each block. If it returns [`ControlFlow::Break`], the derivation
+/// stops early and the content of `output` is unspecified.
+fn pbkdf2<M: Update + FixedOutput + Clone>(
+    keyed_mac: &M,
+    salt: &[u8],
+    iterations: u32,
+    output: &mut [u8],
+    progress: &mut dyn FnMut(u32) -> ControlFlow<()>,
+) -> ControlFlow<()> {
+    debug_assert!((MIN_ENTROPY_LEN..=MAX_EXTENDED_ENTROPY_LEN).contains(&output.len()));
+
+    for (i, block) in output.chunks_mut(M::output_size()).enumerate() {
+        let mut mac = keyed_mac.clone();
+
+        mac.update(salt);
+        mac.update(&(i as u32 + 1).to_be_bytes());
+
+        let mut u = mac.finalize_fixed();
+
+        block.copy_from_slice(&u[..block.len()]);
+
+        // The first iteration is reported with the first chunk.
+        let mut pending = 1;
+        let mut remaining = iterations - 1;
+        let mut flow = ControlFlow::Continue(());
+
+        while flow.is_continue() && (remaining != 0 || pending != 0) {
+            let chunk = remaining.min(PROGRESS_INTERVAL);
+
+            for _ in 0..chunk {
+                let mut mac = keyed_mac.clone();
+
+                mac.update(&u);
+                mac.finalize_into(&mut u);
+
+                for (out, byte) in block.iter_mut().zip(u.iter()) {
+                    *out ^= byte;
+                }
+            }
+
+            remaining -= chunk;
+            flow = progress(chunk + pending);
+            pending = 0;
+        }
+
+        u.as_mut_slice().zeroize();
+
+        flow?;
+    }
+
+    ControlFlow::Continue(())
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::{generate_entropy, generate_extended_entropy_to, generate_salt, get_fingerprint};
+
+    #[test]
+    fn matches_generate_entropy() {
+        let salt = generate_salt("example.org", "contact@example.org", 1);
+
+        for algorithm in [Algorithm::SHA256, Algorithm::SHA384, Algorithm::SHA512] {
+            let key = MasterKey::new("password", algorithm);
+
+            assert!(key.algorithm() == algorithm);
+            assert_eq!(key.fingerprint(), get_fingerprint("password"));
+
+            for iterations in [1, 2, 1_000] {
+                assert_eq!(
+                    key.generate_entropy(&salt, iterations),
+                    generate_entropy("password", &salt, algorithm, iterations),
+                );
+            }
+
+            // Entropy spanning several (and partial) blocks.
+            let mut expected = [0; 150];
+            let mut entropy = [0; 150];
+
+            generate_extended_entropy_to("password", &salt, algorithm, 10, &mut expected);
+            key.generate_extended_entropy_to(&salt, 10, &mut entropy);
+
+            assert_eq!(entropy, expected);
+        }
+    }
+
+    #[test]
+    fn batch() {
+        let salts = (0..13)
+            .map(|i| generate_salt("example.org", "contact@example.org", i))
+            .collect::<std::vec::Vec<_>>();
+
+        for algorithm in [Algorithm::SHA256, Algorithm::SHA512] {
+            let key = MasterKey::new("password", algorithm);
+            let mut outputs = (0..13)
+                .map(|i| std::vec![0; 20 + 10 * i])
+                .collect::<std::vec::Vec<_>>();
+
+            key.generate_entropy_batch_to(&salts, 10, &mut outputs);
+
+            for (salt, output) in salts.iter().zip(&outputs) {
+                let mut expected = std::vec![0; output.len()];
+
+                generate_extended_entropy_to("password", salt, algorithm, 10, &mut expected);
+
+                assert_eq!(output, &expected);
+            }
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        let key = MasterKey::new("password", Algorithm::SHA256);
+
+        assert_eq!(
+            MasterKey::try_new("", Algorithm::SHA256).err(),
+            Some(Error::EmptyMasterPassword),
+        );
+        assert_eq!(key.try_generate_entropy(b"", 1), Err(Error::EmptySalt));
+        assert_eq!(
+            key.try_generate_entropy(b"salt", 0),
+            Err(Error::ZeroIterations)
+        );
+        assert_eq!(
+            key.try_generate_entropy_to(b"salt", 1, &mut [0; 65]),
+            Err(Error::EntropyLengthOutOfRange {
+                min: 1,
+                max: 64,
+                got: 65
+            }),
+        );
+        assert_eq!(
+            key.try_generate_entropy_batch_to(&[b"salt"; 2], 1, &mut [[0; 32]; 3]),
+            Err(Error::OutputCountMismatch {
+                expected: 2,
+                got: 3
+            }),
+        );
+        assert_eq!(
+            key.try_generate_entropy_batch_to(&[&b"salt"[..], b""], 1, &mut [[0; 32]; 2]),
+            Err(Error::EmptySalt),
+        );
+
+        #[cfg(feature = "scrypt")]
+        assert_eq!(
+            MasterKey::try_new("password", Algorithm::Scrypt(Default::default())).err(),
+            Some(Error::UnsupportedAlgorithm),
+        );
+    }
+}
@@ -0,0 +1,127 @@
+//! Unicode normalization of master passwords and logins.
+
+use std::string::String;
+
+use unicode_normalization::UnicodeNormalization as _;
+
+use crate::MasterPassword;
+
+/// A [Unicode normalization form](https://unicode.org/reports/tr15/), used to
+/// encode master passwords and logins the same way regardless of how they
+/// were typed.
+///
+/// For instance, `é` can be typed as a single precomposed character
+/// (`U+00E9`, as on most keyboards) or as `e` followed by a combining accent
+/// (`U+0065 U+0301`, as some macOS input methods do). Both are encoded
+/// differently, and thus yield different passwords unless normalized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NormalizationForm {
+    /// Canonical composition (NFC), which only merges characters that are
+    /// equivalent, such as the two forms of `é`.
+    Nfc,
+    /// Compatibility composition (NFKC), which also replaces characters by
+    /// their compatibility equivalent, e.g. `ﬁ` by `fi` or `Ａ` by `A`.
+    Nfkc,
+}
+
+/// Returns `s` in the given normalization form.
+///
+/// ```
+/// use lesspass::{normalize_unicode, NormalizationForm};
+///
+/// assert_eq!(normalize_unicode("e\u{301}", NormalizationForm::Nfc), "\u{e9}");
+/// assert_eq!(normalize_unicode("\u{fb01}", NormalizationForm::Nfc), "\u{fb01}");
+/// assert_eq!(normalize_unicode("\u{fb01}", NormalizationForm::Nfkc), "fi");
+/// ```
+///
+/// Normalization is opt-in, since it changes the passwords generated from
+/// non-normalized inputs, and other LessPass implementations do not perform
+/// it.
+pub fn normalize_unicode(s: &str, form: NormalizationForm) -> String {
+    match form {
+        NormalizationForm::Nfc => collect_exact(s.nfc()),
+        NormalizationForm::Nfkc => collect_exact(s.nfkc()),
+    }
+}
+
+/// Collects `chars` into a string allocated with the exact length upfront,
+/// so that no copy of the string is left in a freed buffer when it grows.
+fn collect_exact(chars: impl Iterator<Item = char> + Clone) -> String {
+    let len = chars.clone().map(char::len_utf8).sum();
+    let mut string = String::with_capacity(len);
+
+    string.extend(chars);
+    string
+}
+
+impl MasterPassword {
+    /// Returns the master password in the given normalization form.
+    ///
+    /// See [`normalize_unicode`] for more information.
+    pub fn normalized(&self, form: NormalizationForm) -> MasterPassword {
+        MasterPassword::new(normalize_unicode(self.as_str(), form))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate_entropy, generate_salt, Algorithm};
+
+    #[test]
+    fn normalize() {
+        let composed = "caf\u{e9}";
+        let decomposed = "cafe\u{301}";
+
+        for form in [NormalizationForm::Nfc, NormalizationForm::Nfkc] {
+            assert_eq!(normalize_unicode(composed, form), composed);
+            assert_eq!(normalize_unicode(decomposed, form), composed);
+            assert_eq!(normalize_unicode("", form), "");
+        }
+
+        assert_eq!(
+            normalize_unicode("\u{ff2c}ess\u{ff30}ass", NormalizationForm::Nfc),
+            "\u{ff2c}ess\u{ff30}ass",
+        );
+        assert_eq!(
+            normalize_unicode("\u{ff2c}ess\u{ff30}ass", NormalizationForm::Nfkc),
+            "LessPass",
+        );
+    }
+
+    #[test]
+    fn unicode_entropy() {
+        // Same vector as `entropy_tests::unicode`, whose inputs are already
+        // normalized.
+        let expected = "4e66cab40690c01af55efd595f5963cc953d7e10273c01827881ebf8990c627f";
+        let entropy = |login: &str, master_password: &MasterPassword| {
+            let salt = generate_salt("example.org", login, 1);
+            let entropy =
+                generate_entropy(master_password.as_str(), &salt, Algorithm::SHA256, 100_000);
+
+            entropy
+                .iter()
+                .map(|byte| std::format!("{byte:02x}"))
+                .collect::<String>()
+        };
+
+        for form in [NormalizationForm::Nfc, NormalizationForm::Nfkc] {
+            let login = normalize_unicode("❤", form);
+            let master_password = MasterPassword::new("I ❤ LessPass".into()).normalized(form);
+
+            assert_eq!(entropy(&login, &master_password), expected);
+        }
+
+        // Compatibility characters only yield the same entropy with NFKC.
+        let master_password = MasterPassword::new("I ❤ \u{ff2c}ess\u{ff30}ass".into());
+
+        assert_ne!(
+            entropy("❤", &master_password.normalized(NormalizationForm::Nfc)),
+            expected,
+        );
+        assert_eq!(
+            entropy("❤", &master_password.normalized(NormalizationForm::Nfkc)),
+            expected,
+        );
+    }
+}
@@ -0,0 +1,583 @@
+//! Rendering of passphrases (i.e. lists of words picked from a wordlist)
+//! instead of passwords.
+//!
+//! Passphrases are rendered from the same entropy as passwords (see
+//! [`generate_entropy_to`]), which is used as a big-endian number that is
+//! repeatedly divided to pick words, in the same way characters are picked by
+//! [`render_password_to`].
+
+use zeroize::Zeroize;
+
+use crate::ct::Quotient;
+use crate::{
+    ceil_log2, entropy_len_for_bits, error, Error, DEFAULT_WORDLIST, MAX_EXTENDED_ENTROPY_LEN,
+};
+
+#[cfg(doc)]
+use crate::{generate_entropy_to, generate_extended_entropy_to, render_password_to, CharacterSet};
+
+/// The minimum number of words in a passphrase, inclusive.
+pub const MIN_PASSPHRASE_WORDS: usize = 1;
+
+/// The maximum number of words in a passphrase, inclusive.
+pub const MAX_PASSPHRASE_WORDS: usize = 32;
+
+/// How the words of a passphrase are capitalized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capitalization {
+    /// All words are in lowercase.
+    Lowercase,
+
+    /// All words are in uppercase.
+    Uppercase,
+
+    /// The first letter of each word is in uppercase.
+    Title,
+
+    /// The first letter of each word is in uppercase with a probability of
+    /// one half.
+    Random,
+}
+
+/// The settings used to render a passphrase.
+///
+/// Settings are created with [`Passphrase::new`] and can then be customized
+/// using their `with_*` methods:
+///
+/// ```
+/// use lesspass::{Capitalization, Passphrase};
+///
+/// let passphrase = Passphrase::new()
+///     .with_words(4)
+///     .with_separator(" ")
+///     .with_capitalization(Capitalization::Title)
+///     .with_digit(true);
+/// # #[cfg(feature = "std")]
+/// # assert_eq!(
+/// #     lesspass::render_passphrase(&[42; 32], &passphrase).split(' ').count(),
+/// #     4,
+/// # );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Passphrase<'a> {
+    words: usize,
+    wordlist: &'a [&'a str],
+    separator: &'a str,
+    capitalization: Capitalization,
+    digit: bool,
+    symbols: &'a str,
+}
+
+impl<'a> Passphrase<'a> {
+    /// The number of words in a passphrase by default.
+    pub const DEFAULT_WORDS: usize = 6;
+
+    /// The separator between words by default.
+    pub const DEFAULT_SEPARATOR: &'static str = "-";
+
+    /// Creates new passphrase settings: [`Passphrase::DEFAULT_WORDS`] lowercase
+    /// words picked from [`DEFAULT_WORDLIST`] and separated by
+    /// [`Passphrase::DEFAULT_SEPARATOR`], without digits or symbols.
+    pub const fn new() -> Self {
+        Self {
+            words: Self::DEFAULT_WORDS,
+            wordlist: DEFAULT_WORDLIST,
+            separator: Self::DEFAULT_SEPARATOR,
+            capitalization: Capitalization::Lowercase,
+            digit: false,
+            symbols: "",
+        }
+    }
+
+    /// Sets the number of words in the passphrase.
+    pub const fn with_words(mut self, words: usize) -> Self {
+        self.words = words;
+        self
+    }
+
+    /// Sets the list of words the passphrase is made of.
+    ///
+    /// Words are picked with replacement, so the list should not contain
+    /// duplicates.
+    pub const fn with_wordlist(mut self, wordlist: &'a [&'a str]) -> Self {
+        self.wordlist = wordlist;
+        self
+    }
+
+    /// Sets the separator inserted between words.
+    pub const fn with_separator(mut self, separator: &'a str) -> Self {
+        self.separator = separator;
+        self
+    }
+
+    /// Sets how words are capitalized.
+    pub const fn with_capitalization(mut self, capitalization: Capitalization) -> Self {
+        self.capitalization = capitalization;
+        self
+    }
+
+    /// Sets whether a digit is appended to a word of the passphrase.
+    pub const fn with_digit(mut self, digit: bool) -> Self {
+        self.digit = digit;
+        self
+    }
+
+    /// Sets the symbols that may be appended to a word of the passphrase.
+    ///
+    /// If `symbols` is empty (the default), no symbol is added.
+    /// [`CharacterSet::SYMBOLS`] can be used to pick from the same symbols as
+    /// passwords.
+    pub const fn with_symbols(mut self, symbols: &'a str) -> Self {
+        self.symbols = symbols;
+        self
+    }
+
+    /// Returns the number of words in the passphrase.
+    pub const fn words(&self) -> usize {
+        self.words
+    }
+
+    /// Returns the list of words the passphrase is made of.
+    pub const fn wordlist(&self) -> &'a [&'a str] {
+        self.wordlist
+    }
+
+    /// Returns the separator inserted between words.
+    pub const fn separator(&self) -> &'a str {
+        self.separator
+    }
+
+    /// Returns how words are capitalized.
+    pub const fn capitalization(&self) -> Capitalization {
+        self.capitalization
+    }
+
+    /// Returns whether a digit is appended to a word of the passphrase.
+    pub const fn digit(&self) -> bool {
+        self.digit
+    }
+
+    /// Returns the symbols that may be appended to a word of the passphrase.
+    pub const fn symbols(&self) -> &'a str {
+        self.symbols
+    }
+}
+
+impl Default for Passphrase<'_> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the number of bytes of entropy needed to render a passphrase with
+/// the given settings without running out of randomness.
+///
+/// This may be more than the output of [`generate_entropy_to`] for long
+/// passphrases, in which case [`generate_extended_entropy_to`] can be used to
+/// generate enough entropy.
+pub fn passphrase_entropy_len(passphrase: &Passphrase<'_>) -> usize {
+    entropy_len_for_bits(passphrase_bits(passphrase))
+}
+
+/// Returns the number of bits of entropy consumed to render a passphrase with
+/// the given settings.
+fn passphrase_bits(passphrase: &Passphrase<'_>) -> usize {
+    let words = passphrase.words;
+    let mut bits = words * ceil_log2(passphrase.wordlist.len());
+
+    if passphrase.capitalization == Capitalization::Random {
+        bits += words;
+    }
+    if passphrase.digit {
+        bits += ceil_log2(10) + ceil_log2(words);
+    }
+    if !passphrase.symbols.is_empty() {
+        bits += ceil_log2(passphrase.symbols.len()) + ceil_log2(words);
+    }
+
+    bits
+}
+
+/// Renders a passphrase from the given entropy and settings, and writes it to
+/// `output`.
+///
+/// Words are picked first, then whether each word is capitalized (with
+/// [`Capitalization::Random`]), then the digit and the word it is appended to,
+/// and finally the symbol and the word it is appended to.
+///
+/// Returns `Ok(written_size)` if `output` is large enough, and
+/// `Err(required_size)` if it isn't (in which case nothing will be written).
+///
+/// # Panics
+///
+/// Panics if `entropy` is empty or longer than [`MAX_EXTENDED_ENTROPY_LEN`], if
+/// the number of words is not in the
+/// `MIN_PASSPHRASE_WORDS..=MAX_PASSPHRASE_WORDS` range, if the wordlist or one
+/// of its words is empty, if the symbols are not printable ASCII, or if
+/// `entropy` is too short to make all choices (see
+/// [`passphrase_entropy_len`]).
+#[track_caller]
+pub fn render_passphrase_to(
+    entropy: &[u8],
+    passphrase: &Passphrase<'_>,
+    output: &mut [u8],
+) -> Result<usize, usize> {
+    match try_render_passphrase_to(entropy, passphrase, output) {
+        Ok(written) => Ok(written),
+        Err(Error::BufferTooSmall { required }) => Err(required),
+        Err(err) => error::unwrap(Err(err)),
+    }
+}
+
+/// Same as [`render_passphrase_to`], but returns an [`Error`] instead of
+/// panicking if its inputs are invalid.
+///
+/// If `output` is too small, [`Error::BufferTooSmall`] is returned.
+pub fn try_render_passphrase_to(
+    entropy: &[u8],
+    passphrase: &Passphrase<'_>,
+    output: &mut [u8],
+) -> Result<usize, Error> {
+    let choices = Choices::new(entropy, passphrase)?;
+    let len = choices.len(passphrase);
+
+    if output.len() < len {
+        return Err(Error::BufferTooSmall { required: len });
+    }
+
+    choices.write(passphrase, &mut output[..len]);
+
+    Ok(len)
+}
+
+/// Same as [`render_passphrase_to`], but directly returns the rendered
+/// passphrase instead of requiring a mutable output buffer.
+#[cfg(feature = "std")]
+#[track_caller]
+#[inline]
+pub fn render_passphrase(entropy: &[u8], passphrase: &Passphrase<'_>) -> std::string::String {
+    error::unwrap(try_render_passphrase(entropy, passphrase))
+}
+
+/// Same as [`render_passphrase`], but returns an [`Error`] instead of
+/// panicking if its inputs are invalid.
+#[cfg(feature = "std")]
+pub fn try_render_passphrase(
+    entropy: &[u8],
+    passphrase: &Passphrase<'_>,
+) -> Result<std::string::String, Error> {
+    let choices = Choices::new(entropy, passphrase)?;
+    let mut output = std::vec![0; choices.len(passphrase)];
+
+    choices.write(passphrase, &mut output);
+
+    // SAFETY: the output is made of whole words and separators (which are
+    // valid UTF-8, and in which only ASCII characters may be changed), and of
+    // ASCII digits and symbols.
+    Ok(unsafe { std::string::String::from_utf8_unchecked(output) })
+}
+
+/// The random choices made to render a passphrase.
+struct Choices {
+    /// Indices of the words in the wordlist.
+    words: [usize; MAX_PASSPHRASE_WORDS],
+    /// Bit `i` is set if word `i` is capitalized with
+    /// [`Capitalization::Random`].
+    capitalized: u32,
+    /// Digit and index of the word it is appended to.
+    digit: Option<(u8, usize)>,
+    /// Symbol and index of the word it is appended to.
+    symbol: Option<(u8, usize)>,
+}
+
+impl Choices {
+    /// Validates the inputs, and makes all random choices using `entropy`.
+    fn new(entropy: &[u8], passphrase: &Passphrase<'_>) -> Result<Self, Error> {
+        check_passphrase_args(entropy, passphrase)?;
+
+        let words = passphrase.words;
+        let mut quotient = Quotient::from_be_bytes(entropy);
+
+        let mut choices = Choices {
+            words: [0; MAX_PASSPHRASE_WORDS],
+            capitalized: 0,
+            digit: None,
+            symbol: None,
+        };
+
+        for word in &mut choices.words[..words] {
+            *word = quotient.div_rem(passphrase.wordlist.len());
+        }
+
+        if passphrase.capitalization == Capitalization::Random {
+            for i in 0..words {
+                choices.capitalized |= (quotient.div_rem(2) as u32) << i;
+            }
+        }
+
+        if passphrase.digit {
+            let digit = b'0' + quotient.div_rem(10) as u8;
+
+            choices.digit = Some((digit, quotient.div_rem(words)));
+        }
+
+        if !passphrase.symbols.is_empty() {
+            let symbols = passphrase.symbols.as_bytes();
+            let symbol = symbols[quotient.div_rem(symbols.len())];
+
+            choices.symbol = Some((symbol, quotient.div_rem(words)));
+        }
+
+        Ok(choices)
+    }
+
+    /// Returns the length of the rendered passphrase in bytes.
+    fn len(&self, passphrase: &Passphrase<'_>) -> usize {
+        let words = &self.words[..passphrase.words];
+
+        words
+            .iter()
+            .map(|&i| passphrase.wordlist[i].len())
+            .sum::<usize>()
+            + (words.len() - 1) * passphrase.separator.len()
+            + self.digit.is_some() as usize
+            + self.symbol.is_some() as usize
+    }
+
+    /// Writes the passphrase to `output`, which must be exactly
+    /// [`Choices::len`] bytes long.
+    fn write(&self, passphrase: &Passphrase<'_>, output: &mut [u8]) {
+        let mut offset = 0;
+
+        for (i, &word) in self.words[..passphrase.words].iter().enumerate() {
+            if i > 0 {
+                let separator = passphrase.separator.as_bytes();
+
+                output[offset..offset + separator.len()].copy_from_slice(separator);
+                offset += separator.len();
+            }
+
+            let word = passphrase.wordlist[word].as_bytes();
+            let output_word = &mut output[offset..offset + word.len()];
+            let title = match passphrase.capitalization {
+                Capitalization::Lowercase | Capitalization::Uppercase => false,
+                Capitalization::Title => true,
+                Capitalization::Random => self.capitalized & (1 << i) != 0,
+            };
+
+            output_word.copy_from_slice(word);
+
+            if passphrase.capitalization == Capitalization::Uppercase {
+                output_word.make_ascii_uppercase();
+            } else {
+                output_word.make_ascii_lowercase();
+            }
+            if title {
+                output_word[0].make_ascii_uppercase();
+            }
+
+            offset += word.len();
+
+            for (c, index) in [self.digit, self.symbol].into_iter().flatten() {
+                if index == i {
+                    output[offset] = c;
+                    offset += 1;
+                }
+            }
+        }
+
+        debug_assert_eq!(offset, output.len());
+    }
+}
+
+impl Drop for Choices {
+    fn drop(&mut self) {
+        self.words.zeroize();
+        self.capitalized.zeroize();
+        self.digit.zeroize();
+        self.symbol.zeroize();
+    }
+}
+
+/// Returns an [`Error`] if a passphrase cannot be rendered from `entropy` and
+/// `passphrase`.
+fn check_passphrase_args(entropy: &[u8], passphrase: &Passphrase<'_>) -> Result<(), Error> {
+    if entropy.is_empty() {
+        return Err(Error::EmptyEntropy);
+    }
+    if entropy.len() > MAX_EXTENDED_ENTROPY_LEN {
+        return Err(Error::EntropyTooLong {
+            max: MAX_EXTENDED_ENTROPY_LEN,
+            got: entropy.len(),
+        });
+    }
+    if !(MIN_PASSPHRASE_WORDS..=MAX_PASSPHRASE_WORDS).contains(&passphrase.words) {
+        return Err(Error::WordCountOutOfRange {
+            min: MIN_PASSPHRASE_WORDS,
+            max: MAX_PASSPHRASE_WORDS,
+            got: passphrase.words,
+        });
+    }
+    if passphrase.wordlist.is_empty() {
+        return Err(Error::EmptyWordlist);
+    }
+    if let Some(index) = passphrase.wordlist.iter().position(|word| word.is_empty()) {
+        return Err(Error::EmptyWord { index });
+    }
+    if !passphrase.symbols.bytes().all(|c| c.is_ascii_graphic()) {
+        return Err(Error::InvalidSymbols);
+    }
+    // Do not render passphrases from an exhausted entropy, whose last choices
+    // would always be the first word, digit or symbol.
+    if entropy.len() * 8 < passphrase_bits(passphrase) {
+        return Err(Error::EntropyExhausted);
+    }
+
+    Ok(())
+}
+
+// Make sure that `Choices::capitalized` can hold one bit per word.
+const _: () = assert!(MAX_PASSPHRASE_WORDS <= u32::BITS as usize);
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::{generate_entropy, generate_salt, Algorithm, CharacterSet, Entropy};
+
+    fn entropy() -> std::vec::Vec<u8> {
+        let salt = generate_salt("example.org", "contact@example.org", 1);
+
+        generate_entropy("password", &salt, Algorithm::SHA256, 1_000)
+    }
+
+    #[test]
+    fn defaults() {
+        let passphrase = render_passphrase(&entropy(), &Passphrase::new());
+        let words = passphrase.split('-').collect::<std::vec::Vec<_>>();
+
+        assert_eq!(words.len(), Passphrase::DEFAULT_WORDS);
+        assert!(words.iter().all(|word| DEFAULT_WORDLIST.contains(word)));
+    }
+
+    #[test]
+    fn same_words() {
+        let entropy = entropy();
+        let lowercase = render_passphrase(&entropy, &Passphrase::new());
+
+        for capitalization in [
+            Capitalization::Uppercase,
+            Capitalization::Title,
+            Capitalization::Random,
+        ] {
+            let passphrase = Passphrase::new()
+                .with_capitalization(capitalization)
+                .with_separator(" ");
+            let rendered = render_passphrase(&entropy, &passphrase);
+
+            assert_eq!(rendered.to_lowercase(), lowercase.replace('-', " "));
+        }
+
+        assert_eq!(
+            render_passphrase(
+                &entropy,
+                &Passphrase::new().with_capitalization(Capitalization::Uppercase)
+            ),
+            lowercase.to_uppercase(),
+        );
+    }
+
+    #[test]
+    fn digit_and_symbol() {
+        let wordlist = ["a", "b", "c"];
+        let passphrase = Passphrase::new()
+            .with_wordlist(&wordlist)
+            .with_words(10)
+            .with_separator("")
+            .with_digit(true)
+            .with_symbols("!?");
+        let rendered = render_passphrase(&entropy(), &passphrase);
+
+        assert_eq!(rendered.len(), 12);
+        assert_eq!(rendered.bytes().filter(u8::is_ascii_digit).count(), 1);
+        assert_eq!(rendered.bytes().filter(|c| b"!?".contains(c)).count(), 1);
+    }
+
+    #[test]
+    fn render_to() {
+        let entropy = entropy();
+        let passphrase = Passphrase::new().with_digit(true);
+        let expected = render_passphrase(&entropy, &passphrase);
+        let mut output = [0; 128];
+
+        assert_eq!(
+            render_passphrase_to(&entropy, &passphrase, &mut output[..4]),
+            Err(expected.len()),
+        );
+        assert_eq!(
+            render_passphrase_to(&entropy, &passphrase, &mut output),
+            Ok(expected.len()),
+        );
+        assert_eq!(&output[..expected.len()], expected.as_bytes());
+    }
+
+    #[test]
+    fn max_words() {
+        let salt = generate_salt("example.org", "contact@example.org", 1);
+        let passphrase = Passphrase::new()
+            .with_words(MAX_PASSPHRASE_WORDS)
+            .with_capitalization(Capitalization::Random)
+            .with_digit(true)
+            .with_symbols(CharacterSet::SYMBOLS);
+        let len = passphrase_entropy_len(&passphrase);
+
+        assert!(len > Algorithm::SHA256.entropy_len());
+        assert_eq!(
+            try_render_passphrase(&entropy(), &passphrase),
+            Err(Error::EntropyExhausted),
+        );
+
+        let entropy =
+            Entropy::generate_extended("password", &salt, Algorithm::SHA256, 1_000, len).unwrap();
+        let rendered = render_passphrase(entropy.as_bytes(), &passphrase);
+        let words = rendered.split('-').collect::<std::vec::Vec<_>>();
+
+        assert_eq!(words.len(), MAX_PASSPHRASE_WORDS);
+        // The last words are not all the first word of the wordlist, as they
+        // would be if the entropy had been exhausted.
+        assert!(words[MAX_PASSPHRASE_WORDS - 8..]
+            .iter()
+            .any(|word| !word.eq_ignore_ascii_case(DEFAULT_WORDLIST[0])));
+    }
+
+    #[test]
+    fn invalid() {
+        let entropy = entropy();
+        let render = |passphrase: Passphrase| try_render_passphrase(&entropy, &passphrase).err();
+
+        assert_eq!(
+            try_render_passphrase(&[], &Passphrase::new()).err(),
+            Some(Error::EmptyEntropy),
+        );
+        assert_eq!(
+            render(Passphrase::new().with_words(0)),
+            Some(Error::WordCountOutOfRange {
+                min: MIN_PASSPHRASE_WORDS,
+                max: MAX_PASSPHRASE_WORDS,
+                got: 0
+            }),
+        );
+        assert_eq!(
+            render(Passphrase::new().with_wordlist(&[])),
+            Some(Error::EmptyWordlist),
+        );
+        assert_eq!(
+            render(Passphrase::new().with_wordlist(&["a", ""])),
+            Some(Error::EmptyWord { index: 1 }),
+        );
+        assert_eq!(
+            render(Passphrase::new().with_symbols("é")),
+            Some(Error::InvalidSymbols),
+        );
+    }
+}
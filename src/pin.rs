@@ -0,0 +1,300 @@
+//! Rendering of numeric PINs.
+//!
+//! PINs are rendered from the same entropy as passwords, but unlike passwords
+//! rendered with [`CharacterSet::Digits`], they may be as short as
+//! [`MIN_PIN_LEN`] digits, and PINs that are easy to guess (e.g. `1111`,
+//! `1234` or `1987`) are rejected: more digits are then drawn from the
+//! entropy until a PIN that is not weak is found.
+
+use core::mem::MaybeUninit;
+
+use zeroize::Zeroizing;
+
+use crate::ct::Quotient;
+use crate::{error, slice_to_maybe_uninit_mut, Error, MAX_ENTROPY_LEN, MIN_ENTROPY_LEN};
+
+#[cfg(doc)]
+use crate::CharacterSet;
+
+/// The minimum length of a PIN, inclusive.
+pub const MIN_PIN_LEN: usize = 4;
+
+/// The maximum length of a PIN, inclusive.
+pub const MAX_PIN_LEN: usize = 12;
+
+/// Same as [`render_pin_to`], but works with an uninitialized output buffer,
+/// which is okay since it only writes to it, without reading from it.
+#[track_caller]
+#[inline]
+pub fn render_pin_to_uninit<'out>(
+    entropy: &[u8],
+    output: &'out mut [MaybeUninit<u8>],
+) -> &'out mut [u8] {
+    error::unwrap(try_render_pin_to_uninit(entropy, output))
+}
+
+/// Same as [`render_pin_to_uninit`], but returns an [`Error`] instead of
+/// panicking if its inputs are invalid.
+pub fn try_render_pin_to_uninit<'out>(
+    entropy: &[u8],
+    output: &'out mut [MaybeUninit<u8>],
+) -> Result<&'out mut [u8], Error> {
+    check_pin_args(entropy, output.len())?;
+
+    let len = output.len();
+    let min_quotient = 10u64.pow(len as u32);
+    let mut quotient = Quotient::from_be_bytes(entropy);
+    let mut pin = Zeroizing::new([0; MAX_PIN_LEN]);
+    let pin = &mut pin[..len];
+
+    // Digits are extracted in constant time, but whether a PIN is weak (and
+    // thus how many PINs are drawn) necessarily depends on its digits.
+    loop {
+        // Do not render PINs from an exhausted entropy, which would be biased
+        // towards leading zeros.
+        if quotient.is_less_than(min_quotient) {
+            return Err(Error::EntropyExhausted);
+        }
+
+        let digits = quotient.digits(core::iter::repeat(10).take(len));
+
+        for (digit, value) in pin.iter_mut().zip(digits) {
+            *digit = b'0' + value as u8;
+        }
+
+        if !is_weak_pin(pin) {
+            break;
+        }
+    }
+
+    let output = &mut output[..len];
+
+    for (out, &digit) in output.iter_mut().zip(pin.iter()) {
+        *out = MaybeUninit::new(digit);
+    }
+
+    // SAFETY: all bytes of `output` were written to (and thus initialized).
+    Ok(unsafe { &mut *(output as *mut [MaybeUninit<u8>] as *mut [u8]) })
+}
+
+/// Renders a PIN of `output.len()` digits using the given entropy, skipping
+/// PINs made of a repeated pattern (e.g. `1111` or `1212`), of a sequence of
+/// digits (e.g. `1234` or `8765`), or that look like a date (e.g. `1231`,
+/// `1987` or `31121999`).
+///
+/// # Panics
+///
+/// Panics if `entropy` is empty or longer than [`MAX_ENTROPY_LEN`], or if
+/// `output` is smaller than [`MIN_PIN_LEN`] or greater than [`MAX_PIN_LEN`], or
+/// if `entropy` is exhausted before a PIN that is not weak is found (which
+/// is extremely unlikely with the output of
+/// [`generate_entropy_to`](crate::generate_entropy_to)).
+#[track_caller]
+#[inline]
+pub fn render_pin_to(entropy: &[u8], output: &mut [u8]) {
+    error::unwrap(try_render_pin_to(entropy, output))
+}
+
+/// Same as [`render_pin_to`], but returns an [`Error`] instead of panicking if
+/// its inputs are invalid.
+#[inline]
+pub fn try_render_pin_to(entropy: &[u8], output: &mut [u8]) -> Result<(), Error> {
+    try_render_pin_to_uninit(entropy, slice_to_maybe_uninit_mut(output)).map(|_| ())
+}
+
+/// Same as [`render_pin_to`], but directly returns the rendered PIN instead of
+/// requiring a mutable output buffer.
+#[cfg(feature = "std")]
+#[track_caller]
+#[inline]
+pub fn render_pin(entropy: &[u8], len: usize) -> std::string::String {
+    error::unwrap(try_render_pin(entropy, len))
+}
+
+/// Same as [`render_pin`], but returns an [`Error`] instead of panicking if its
+/// inputs are invalid.
+#[cfg(feature = "std")]
+pub fn try_render_pin(entropy: &[u8], len: usize) -> Result<std::string::String, Error> {
+    // Validate arguments before allocating the output buffer.
+    check_pin_args(entropy, len)?;
+
+    let mut uninit_output = crate::uninit_vec(len);
+
+    try_render_pin_to_uninit(entropy, &mut uninit_output)?;
+
+    // SAFETY: `uninit_output` was fully initialized in
+    // `try_render_pin_to_uninit`.
+    let output = unsafe { crate::assume_init_vec(uninit_output) };
+
+    // SAFETY: the PIN only contains ASCII digits.
+    Ok(unsafe { std::string::String::from_utf8_unchecked(output) })
+}
+
+/// Returns an [`Error`] if a PIN of length `len` cannot be rendered from
+/// `entropy`.
+fn check_pin_args(entropy: &[u8], len: usize) -> Result<(), Error> {
+    if entropy.len() < MIN_ENTROPY_LEN {
+        return Err(Error::EmptyEntropy);
+    }
+    if entropy.len() > MAX_ENTROPY_LEN {
+        return Err(Error::EntropyTooLong {
+            max: MAX_ENTROPY_LEN,
+            got: entropy.len(),
+        });
+    }
+    if !(MIN_PIN_LEN..=MAX_PIN_LEN).contains(&len) {
+        return Err(Error::LengthOutOfRange {
+            min: MIN_PIN_LEN,
+            max: MAX_PIN_LEN,
+            got: len,
+        });
+    }
+
+    Ok(())
+}
+
+/// Returns whether the given PIN (made of ASCII digits) is easy to guess.
+fn is_weak_pin(pin: &[u8]) -> bool {
+    is_repeated(pin) || is_sequence(pin) || is_date(pin)
+}
+
+/// Returns whether `pin` is a shorter pattern repeated several times, e.g.
+/// `1111`, `1212` or `123123`.
+fn is_repeated(pin: &[u8]) -> bool {
+    (1..pin.len())
+        .filter(|period| pin.len() % period == 0)
+        .any(|period| (period..pin.len()).all(|i| pin[i] == pin[i - period]))
+}
+
+/// Returns whether `pin` is an ascending or descending sequence of digits,
+/// e.g. `1234`, `7890` or `4321`.
+fn is_sequence(pin: &[u8]) -> bool {
+    let step = |a: u8, b: u8| (10 + b - a) % 10;
+
+    [1, 9]
+        .into_iter()
+        .any(|expected| pin.windows(2).all(|w| step(w[0], w[1]) == expected))
+}
+
+/// Returns whether `pin` looks like a date, e.g. `0214`, `1402`, `1987`,
+/// `140287` or `19870214`.
+fn is_date(pin: &[u8]) -> bool {
+    let num = |digits: &[u8]| {
+        digits
+            .iter()
+            .fold(0, |acc, &digit| acc * 10 + u32::from(digit - b'0'))
+    };
+    let is_day_month = |day: u32, month: u32| {
+        let days = match month {
+            2 => 29,
+            4 | 6 | 9 | 11 => 30,
+            1..=12 => 31,
+            _ => return false,
+        };
+
+        (1..=days).contains(&day)
+    };
+    let is_year = |year: u32| (1900..=2099).contains(&year);
+
+    match pin.len() {
+        // DDMM, MMDD or YYYY.
+        4 => {
+            let (a, b) = (num(&pin[..2]), num(&pin[2..]));
+
+            is_day_month(a, b) || is_day_month(b, a) || is_year(num(pin))
+        }
+        // DDMMYY, MMDDYY or YYMMDD.
+        6 => {
+            let (a, b, c) = (num(&pin[..2]), num(&pin[2..4]), num(&pin[4..]));
+
+            is_day_month(a, b) || is_day_month(b, a) || is_day_month(c, b)
+        }
+        // DDMMYYYY, MMDDYYYY or YYYYMMDD.
+        8 => {
+            let (a, b) = (num(&pin[..2]), num(&pin[2..4]));
+            let (c, d) = (num(&pin[4..6]), num(&pin[6..]));
+
+            ((is_day_month(a, b) || is_day_month(b, a)) && is_year(num(&pin[4..])))
+                || (is_year(num(&pin[..4])) && is_day_month(d, c))
+        }
+        _ => false,
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::{generate_entropy, generate_salt, Algorithm};
+
+    #[test]
+    fn weak_pins() {
+        for pin in [
+            "0000",
+            "1111",
+            "1212",
+            "123123",
+            "1234",
+            "7890",
+            "9876",
+            "3210",
+            "0214",
+            "3112",
+            "1987",
+            "2024",
+            "140287",
+            "870214",
+            "31121999",
+            "19870214",
+            "123456789012",
+        ] {
+            assert!(is_weak_pin(pin.as_bytes()), "{pin} should be weak");
+        }
+
+        for pin in ["1357", "3254", "7391", "0000001", "123457", "99119912"] {
+            assert!(!is_weak_pin(pin.as_bytes()), "{pin} should not be weak");
+        }
+    }
+
+    #[test]
+    fn render() {
+        let salt = generate_salt("example.org", "contact@example.org", 1);
+        let entropy = generate_entropy("password", &salt, Algorithm::SHA256, 1_000);
+
+        for len in MIN_PIN_LEN..=MAX_PIN_LEN {
+            let pin = render_pin(&entropy, len);
+
+            assert_eq!(pin.len(), len);
+            assert!(pin.bytes().all(|c| c.is_ascii_digit()));
+            assert!(!is_weak_pin(pin.as_bytes()));
+        }
+    }
+
+    #[test]
+    fn skips_weak_pins() {
+        // 1111 (weak) is drawn first, followed by 8642 (rendered in reverse).
+        let entropy = 24_681_111_u64.to_be_bytes();
+
+        assert_eq!(try_render_pin(&entropy, 4), Err(Error::EntropyExhausted),);
+
+        let entropy = 1_024_681_111_u64.to_be_bytes();
+
+        assert_eq!(render_pin(&entropy, 4), "8642");
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(try_render_pin(&[], 4), Err(Error::EmptyEntropy));
+        assert_eq!(
+            try_render_pin(&[1; 65], 4),
+            Err(Error::EntropyTooLong { max: 64, got: 65 }),
+        );
+        assert_eq!(
+            try_render_pin(&[1; 32], 3),
+            Err(Error::LengthOutOfRange {
+                min: MIN_PIN_LEN,
+                max: MAX_PIN_LEN,
+                got: 3
+            }),
+        );
+    }
+}
@@ -0,0 +1,262 @@
+//! Requirements that websites enforce on passwords, beyond the classes of
+//! characters they contain.
+
+#[cfg(doc)]
+use crate::{Alphabet, Profile};
+
+/// The maximum number of counters tried by
+/// [`Profile::generate_with_policy`].
+pub const MAX_POLICY_ATTEMPTS: u32 = 100;
+
+/// Requirements on passwords, such as the ones enforced by some websites
+/// (e.g. "at least two digits" or "no more than two identical characters in
+/// a row").
+///
+/// Rendered passwords always contain at least one character of each class of
+/// their [`Alphabet`], but other requirements can only be satisfied by
+/// generating passwords until one of them is accepted, which
+/// [`Profile::generate_with_policy`] does by incrementing the counter of a
+/// profile.
+///
+/// Policies are created with [`PasswordPolicy::new`], which accepts all
+/// passwords, and can then be customized using their `with_*` methods:
+///
+/// ```
+/// use lesspass::PasswordPolicy;
+///
+/// let policy = PasswordPolicy::new()
+///     .with_min_digits(2)
+///     .with_max_consecutive(2)
+///     .with_letter_first(true);
+///
+/// assert!(policy.is_satisfied_by("a1b22c"));
+/// assert!(!policy.is_satisfied_by("1ab22c"));
+/// assert!(!policy.is_satisfied_by("a1b222"));
+/// assert!(!policy.is_satisfied_by("abc2de"));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PasswordPolicy<'a> {
+    min_lowercase: usize,
+    min_uppercase: usize,
+    min_digits: usize,
+    min_symbols: usize,
+    max_consecutive: Option<usize>,
+    forbidden: &'a str,
+    letter_first: bool,
+    max_len: Option<usize>,
+}
+
+impl<'a> PasswordPolicy<'a> {
+    /// Creates a policy which accepts all passwords.
+    pub const fn new() -> Self {
+        Self {
+            min_lowercase: 0,
+            min_uppercase: 0,
+            min_digits: 0,
+            min_symbols: 0,
+            max_consecutive: None,
+            forbidden: "",
+            letter_first: false,
+            max_len: None,
+        }
+    }
+
+    /// Sets the minimum number of lowercase letters in the password.
+    pub const fn with_min_lowercase(mut self, min: usize) -> Self {
+        self.min_lowercase = min;
+        self
+    }
+
+    /// Sets the minimum number of uppercase letters in the password.
+    pub const fn with_min_uppercase(mut self, min: usize) -> Self {
+        self.min_uppercase = min;
+        self
+    }
+
+    /// Sets the minimum number of digits in the password.
+    pub const fn with_min_digits(mut self, min: usize) -> Self {
+        self.min_digits = min;
+        self
+    }
+
+    /// Sets the minimum number of symbols (i.e. characters that are neither
+    /// letters nor digits) in the password.
+    pub const fn with_min_symbols(mut self, min: usize) -> Self {
+        self.min_symbols = min;
+        self
+    }
+
+    /// Sets the maximum number of identical characters in a row in the
+    /// password.
+    pub const fn with_max_consecutive(mut self, max: usize) -> Self {
+        self.max_consecutive = Some(max);
+        self
+    }
+
+    /// Sets characters which must not appear in the password.
+    pub const fn with_forbidden(mut self, forbidden: &'a str) -> Self {
+        self.forbidden = forbidden;
+        self
+    }
+
+    /// Sets whether the password must start with a letter.
+    pub const fn with_letter_first(mut self, letter_first: bool) -> Self {
+        self.letter_first = letter_first;
+        self
+    }
+
+    /// Sets the maximum length of the password.
+    pub const fn with_max_len(mut self, max: usize) -> Self {
+        self.max_len = Some(max);
+        self
+    }
+
+    /// Returns the minimum number of lowercase letters in the password.
+    pub const fn min_lowercase(&self) -> usize {
+        self.min_lowercase
+    }
+
+    /// Returns the minimum number of uppercase letters in the password.
+    pub const fn min_uppercase(&self) -> usize {
+        self.min_uppercase
+    }
+
+    /// Returns the minimum number of digits in the password.
+    pub const fn min_digits(&self) -> usize {
+        self.min_digits
+    }
+
+    /// Returns the minimum number of symbols in the password.
+    pub const fn min_symbols(&self) -> usize {
+        self.min_symbols
+    }
+
+    /// Returns the maximum number of identical characters in a row in the
+    /// password, if limited.
+    pub const fn max_consecutive(&self) -> Option<usize> {
+        self.max_consecutive
+    }
+
+    /// Returns the characters which must not appear in the password.
+    pub const fn forbidden(&self) -> &'a str {
+        self.forbidden
+    }
+
+    /// Returns whether the password must start with a letter.
+    pub const fn letter_first(&self) -> bool {
+        self.letter_first
+    }
+
+    /// Returns the maximum length of the password, if limited.
+    pub const fn max_len(&self) -> Option<usize> {
+        self.max_len
+    }
+
+    /// Returns whether `password` satisfies all the requirements of the
+    /// policy.
+    pub fn is_satisfied_by(&self, password: &str) -> bool {
+        let count = |f: fn(&u8) -> bool| password.bytes().filter(f).count();
+
+        if count(u8::is_ascii_lowercase) < self.min_lowercase
+            || count(u8::is_ascii_uppercase) < self.min_uppercase
+            || count(u8::is_ascii_digit) < self.min_digits
+            || count(|c| !c.is_ascii_alphanumeric()) < self.min_symbols
+        {
+            return false;
+        }
+        if password.chars().any(|c| self.forbidden.contains(c)) {
+            return false;
+        }
+        if self.letter_first && !password.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            return false;
+        }
+        if self.max_len.is_some_and(|max| password.len() > max) {
+            return false;
+        }
+        if let Some(max) = self.max_consecutive {
+            let mut run = 0;
+            let mut previous = None;
+
+            for c in password.chars() {
+                run = if previous == Some(c) { run + 1 } else { 1 };
+                previous = Some(c);
+
+                if run > max {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Returns whether passwords of `len` characters can satisfy the
+    /// minimum numbers of characters and the maximum length of the policy.
+    #[cfg(feature = "std")]
+    pub(crate) fn fits(&self, len: usize) -> bool {
+        let min_len = self.min_lowercase + self.min_uppercase + self.min_digits + self.min_symbols;
+
+        min_len <= len && self.max_len.map_or(true, |max| len <= max)
+    }
+}
+
+impl Default for PasswordPolicy<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn satisfied() {
+        let policy = PasswordPolicy::new();
+
+        assert!(policy.is_satisfied_by(""));
+        assert!(policy.is_satisfied_by("aaaaaaaa"));
+
+        let policy = PasswordPolicy::new()
+            .with_min_lowercase(1)
+            .with_min_uppercase(2)
+            .with_min_digits(3)
+            .with_min_symbols(1);
+
+        assert!(policy.is_satisfied_by("aBC123-"));
+        assert!(!policy.is_satisfied_by("BC123-"));
+        assert!(!policy.is_satisfied_by("aC123-"));
+        assert!(!policy.is_satisfied_by("aBC12-"));
+        assert!(!policy.is_satisfied_by("aBC123"));
+
+        let policy = PasswordPolicy::new().with_max_consecutive(2);
+
+        assert!(policy.is_satisfied_by("aabbaa"));
+        assert!(!policy.is_satisfied_by("abbba"));
+
+        let policy = PasswordPolicy::new().with_forbidden("\"'");
+
+        assert!(policy.is_satisfied_by("abc"));
+        assert!(!policy.is_satisfied_by("a'bc"));
+
+        let policy = PasswordPolicy::new().with_letter_first(true);
+
+        assert!(policy.is_satisfied_by("Z0"));
+        assert!(!policy.is_satisfied_by("0Z"));
+        assert!(!policy.is_satisfied_by(""));
+
+        let policy = PasswordPolicy::new().with_max_len(3);
+
+        assert!(policy.is_satisfied_by("abc"));
+        assert!(!policy.is_satisfied_by("abcd"));
+    }
+
+    #[test]
+    fn fits() {
+        let policy = PasswordPolicy::new().with_min_digits(3).with_min_symbols(2);
+
+        assert!(policy.fits(5));
+        assert!(!policy.fits(4));
+        assert!(!policy.with_max_len(8).fits(9));
+    }
+}
@@ -0,0 +1,795 @@
+use core::mem::MaybeUninit;
+
+use zeroize::Zeroizing;
+
+use crate::{
+    error, extended_entropy_len, slice_to_maybe_uninit_mut, try_render_extended_password_to_uninit,
+    try_render_password_to_uninit, write_salt, Algorithm, Alphabet, CharacterSet, Entropy, Error,
+    Kdf, MAX_EXTENDED_PASSWORD_LEN, MAX_PASSWORD_LEN, MIN_PASSWORD_LEN,
+};
+
+/// The maximum length of the salt (website, login and counter) that
+/// [`Profile::generate_to`] can handle without allocating, in bytes.
+const MAX_SALT_LEN: usize = 1024;
+
+/// All the parameters needed to generate the password of a single website.
+///
+/// A profile is created with [`Profile::new`], which uses the same defaults as
+/// the canonical LessPass implementation, and can then be customized using its
+/// `with_*` methods:
+///
+/// ```
+/// use lesspass::{CharacterSet, Profile};
+///
+/// let profile = Profile::new("example.org", "contact@example.org")
+///     .with_length(20)
+///     .with_charset(CharacterSet::Letters | CharacterSet::Digits);
+/// # #[cfg(feature = "std")]
+/// # assert_eq!(profile.generate("password").as_str().len(), 20);
+/// ```
+///
+/// Profiles use an [`Algorithm`] to derive entropy by default, but may use
+/// any other [`Kdf`] given to [`Profile::with_kdf`].
+#[derive(Clone, Copy)]
+pub struct Profile<'a, K = Algorithm> {
+    website: &'a str,
+    login: &'a str,
+    counter: u64,
+    length: usize,
+    alphabet: Alphabet<'a>,
+    kdf: K,
+    iterations: u32,
+    extended: bool,
+    strict: bool,
+}
+
+impl<'a> Profile<'a> {
+    /// The counter used by default.
+    pub const DEFAULT_COUNTER: u64 = 1;
+
+    /// The length of the password generated by default.
+    pub const DEFAULT_LENGTH: usize = 16;
+
+    /// The number of PBKDF2 iterations used by default.
+    pub const DEFAULT_ITERATIONS: u32 = 100_000;
+
+    /// Creates a new profile for the given website and login, using the
+    /// defaults of LessPass: SHA-256 with 100,000 iterations, a counter of 1,
+    /// and a password of 16 characters using all character sets.
+    pub const fn new(website: &'a str, login: &'a str) -> Self {
+        Self {
+            website,
+            login,
+            counter: Self::DEFAULT_COUNTER,
+            length: Self::DEFAULT_LENGTH,
+            alphabet: Alphabet::from_charset(CharacterSet::All),
+            kdf: Algorithm::SHA256,
+            iterations: Self::DEFAULT_ITERATIONS,
+            extended: false,
+            strict: false,
+        }
+    }
+
+    /// Sets the hash algorithm used by PBKDF2.
+    pub const fn with_algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.kdf = algorithm;
+        self
+    }
+
+    /// Returns the hash algorithm used by PBKDF2.
+    pub const fn algorithm(&self) -> Algorithm {
+        self.kdf
+    }
+}
+
+impl<'a, K: Kdf> Profile<'a, K> {
+    /// Sets the counter of the profile.
+    ///
+    /// The counter must be at most [`u32::MAX`], or
+    /// [`MAX_STRICT_COUNTER`](crate::MAX_STRICT_COUNTER) with
+    /// [`Profile::with_strict`].
+    pub const fn with_counter(mut self, counter: u64) -> Self {
+        self.counter = counter;
+        self
+    }
+
+    /// Sets the length of the generated password.
+    pub const fn with_length(mut self, length: usize) -> Self {
+        self.length = length;
+        self
+    }
+
+    /// Sets the characters that may appear in the generated password.
+    pub const fn with_charset(mut self, charset: CharacterSet) -> Self {
+        self.alphabet = Alphabet::from_charset(charset);
+        self
+    }
+
+    /// Sets the classes of characters that may appear in the generated
+    /// password, replacing the character set given to
+    /// [`Profile::with_charset`].
+    pub const fn with_alphabet(mut self, alphabet: Alphabet<'a>) -> Self {
+        self.alphabet = alphabet;
+        self
+    }
+
+    /// Sets the function used to derive entropy, replacing the algorithm given
+    /// to [`Profile::with_algorithm`].
+    pub fn with_kdf<K2: Kdf>(self, kdf: K2) -> Profile<'a, K2> {
+        Profile {
+            website: self.website,
+            login: self.login,
+            counter: self.counter,
+            length: self.length,
+            alphabet: self.alphabet,
+            kdf,
+            iterations: self.iterations,
+            extended: self.extended,
+            strict: self.strict,
+        }
+    }
+
+    /// Sets the number of iterations used by PBKDF2.
+    pub const fn with_iterations(mut self, iterations: u32) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    /// Sets whether passwords longer than [`MAX_PASSWORD_LEN`] may be
+    /// generated.
+    ///
+    /// Passwords of at most [`MAX_PASSWORD_LEN`] characters are always
+    /// generated using the canonical LessPass algorithm. Longer passwords (up
+    /// to [`MAX_EXTENDED_PASSWORD_LEN`] characters) are generated using as much
+    /// entropy as needed; see [`extended_entropy_len`].
+    pub const fn with_extended(mut self, extended: bool) -> Self {
+        self.extended = extended;
+        self
+    }
+
+    /// Sets whether salts strictly follow the canonical LessPass
+    /// implementation, which only differs for a counter of 0; see
+    /// [`generate_strict_salt_to`](crate::generate_strict_salt_to).
+    pub const fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Returns the target website.
+    pub const fn website(&self) -> &'a str {
+        self.website
+    }
+
+    /// Returns the username or email address.
+    pub const fn login(&self) -> &'a str {
+        self.login
+    }
+
+    /// Returns the counter.
+    pub const fn counter(&self) -> u64 {
+        self.counter
+    }
+
+    /// Returns the length of the generated password.
+    pub const fn length(&self) -> usize {
+        self.length
+    }
+
+    /// Returns the classes of characters that may appear in the generated
+    /// password.
+    pub const fn alphabet(&self) -> &Alphabet<'a> {
+        &self.alphabet
+    }
+
+    /// Returns the function used to derive entropy.
+    pub const fn kdf(&self) -> &K {
+        &self.kdf
+    }
+
+    /// Returns the number of iterations used by PBKDF2.
+    pub const fn iterations(&self) -> u32 {
+        self.iterations
+    }
+
+    /// Returns whether passwords longer than [`MAX_PASSWORD_LEN`] may be
+    /// generated.
+    pub const fn extended(&self) -> bool {
+        self.extended
+    }
+
+    /// Returns whether salts strictly follow the canonical LessPass
+    /// implementation.
+    pub const fn strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Generates the password that corresponds to this profile and the given
+    /// master password, and writes it to `output`.
+    ///
+    /// Returns `Ok(written_size)` if `output` is large enough, and
+    /// `Err(required_size)` if it isn't (in which case nothing will be written).
+    ///
+    /// # Panics
+    ///
+    /// Panics in the same cases as [`generate_entropy_to`](crate::generate_entropy_to)
+    /// and [`render_password_to`](crate::render_password_to), or if the combined
+    /// length of the website, login and counter exceeds 1024 bytes. See
+    /// [`Profile::try_generate_to`] for a version of this function that returns
+    /// an [`Error`] instead.
+    #[track_caller]
+    pub fn generate_to(&self, master_password: &str, output: &mut [u8]) -> Result<usize, usize> {
+        match self.try_generate_to(master_password, output) {
+            Ok(written) => Ok(written),
+            Err(Error::BufferTooSmall { required }) => Err(required),
+            Err(err) => error::unwrap(Err(err)),
+        }
+    }
+
+    /// Same as [`Profile::generate_to`], but returns an [`Error`] instead of
+    /// panicking if the profile or master password are invalid.
+    ///
+    /// If `output` is too small, [`Error::BufferTooSmall`] is returned.
+    pub fn try_generate_to(
+        &self,
+        master_password: &str,
+        output: &mut [u8],
+    ) -> Result<usize, Error> {
+        if output.len() < self.length {
+            return Err(Error::BufferTooSmall {
+                required: self.length,
+            });
+        }
+
+        self.check_length()?;
+        self.check_counter(self.counter)?;
+
+        let mut salt_buf = Zeroizing::new([MaybeUninit::uninit(); MAX_SALT_LEN]);
+        let salt = match write_salt(
+            self.website,
+            self.login,
+            self.counter,
+            self.strict,
+            &mut *salt_buf,
+        ) {
+            Ok(salt) => salt,
+            Err(got) => {
+                return Err(Error::SaltTooLong {
+                    max: MAX_SALT_LEN,
+                    got,
+                })
+            }
+        };
+
+        self.generate_with_salt(
+            master_password,
+            salt,
+            slice_to_maybe_uninit_mut(&mut output[..self.length]),
+        )?;
+
+        Ok(self.length)
+    }
+
+    /// Same as [`Profile::generate_to`], but directly returns the password
+    /// instead of requiring a mutable output buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics in the same cases as [`generate_entropy`](crate::generate_entropy)
+    /// and [`render_password`](crate::render_password).
+    #[cfg(feature = "std")]
+    #[track_caller]
+    pub fn generate(&self, master_password: &str) -> crate::Password {
+        error::unwrap(self.try_generate(master_password))
+    }
+
+    /// Same as [`Profile::generate`], but returns an [`Error`] instead of
+    /// panicking if the profile or master password are invalid.
+    #[cfg(feature = "std")]
+    pub fn try_generate(&self, master_password: &str) -> Result<crate::Password, Error> {
+        // Validate the length before allocating the output buffer.
+        self.check_length()?;
+        self.check_counter(self.counter)?;
+
+        let salt = Zeroizing::new(self.salt(self.counter));
+        let mut uninit_output = crate::uninit_vec(self.length);
+
+        self.generate_with_salt(master_password, &salt, &mut uninit_output)?;
+
+        // SAFETY: `uninit_output` was fully initialized in `generate_with_salt`.
+        Ok(unsafe { password_from_uninit(uninit_output) })
+    }
+
+    /// Same as [`Profile::generate`], but increments the counter of the
+    /// profile until the password satisfies `policy`, and returns it along
+    /// with the counter it was generated with.
+    ///
+    /// Counters are tried in order, starting from [`Profile::counter`], so the
+    /// same counter is always found for the same inputs, and it can be used
+    /// in other LessPass implementations to get the same password. At most
+    /// [`MAX_POLICY_ATTEMPTS`](crate::MAX_POLICY_ATTEMPTS) counters are tried.
+    ///
+    /// ```
+    /// use lesspass::{PasswordPolicy, Profile};
+    ///
+    /// let profile = Profile::new("example.org", "contact@example.org");
+    /// let policy = PasswordPolicy::new().with_min_digits(3).with_letter_first(true);
+    /// let (password, counter) = profile.generate_with_policy("password", &policy);
+    ///
+    /// assert!(policy.is_satisfied_by(password.as_str()));
+    /// assert_eq!(
+    ///     password.as_str(),
+    ///     profile.with_counter(counter).generate("password").as_str(),
+    /// );
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics in the same cases as [`Profile::generate`], if the length of the
+    /// profile cannot satisfy the policy, or if no password satisfying the
+    /// policy is found. See [`Profile::try_generate_with_policy`] for a
+    /// version of this function that returns an [`Error`] instead.
+    #[cfg(feature = "std")]
+    #[track_caller]
+    pub fn generate_with_policy(
+        &self,
+        master_password: &str,
+        policy: &crate::PasswordPolicy<'_>,
+    ) -> (crate::Password, u64) {
+        error::unwrap(self.try_generate_with_policy(master_password, policy))
+    }
+
+    /// Same as [`Profile::generate_with_policy`], but returns an [`Error`]
+    /// instead of panicking if the profile or master password are invalid,
+    /// or if no password satisfying the policy is found.
+    ///
+    /// If the length of the profile is greater than the maximum length of the
+    /// policy, [`Error::LengthOutOfRange`] is returned; if it is smaller than
+    /// the sum of the minimum numbers of characters of the policy, or if none
+    /// of the tried counters yields a satisfying password,
+    /// [`Error::PolicyNotSatisfied`] is returned. Counters that are too large
+    /// for the profile are not tried.
+    #[cfg(feature = "std")]
+    pub fn try_generate_with_policy(
+        &self,
+        master_password: &str,
+        policy: &crate::PasswordPolicy<'_>,
+    ) -> Result<(crate::Password, u64), Error> {
+        self.check_length()?;
+        self.check_counter(self.counter)?;
+
+        if let Some(max) = policy.max_len() {
+            if self.length > max {
+                return Err(Error::LengthOutOfRange {
+                    min: MIN_PASSWORD_LEN.max(self.alphabet.classes().len() + 1),
+                    max,
+                    got: self.length,
+                });
+            }
+        }
+        if !policy.fits(self.length) {
+            return Err(Error::PolicyNotSatisfied { attempts: 0 });
+        }
+
+        let mut attempts = 0;
+
+        while attempts < crate::MAX_POLICY_ATTEMPTS {
+            let Some(counter) = self
+                .counter
+                .checked_add(attempts.into())
+                .filter(|&counter| self.check_counter(counter).is_ok())
+            else {
+                break;
+            };
+            let salt = Zeroizing::new(self.salt(counter));
+            let mut uninit_output = crate::uninit_vec(self.length);
+
+            self.generate_with_salt(master_password, &salt, &mut uninit_output)?;
+
+            // SAFETY: `uninit_output` was fully initialized in `generate_with_salt`.
+            let password = unsafe { password_from_uninit(uninit_output) };
+
+            attempts += 1;
+
+            if policy.is_satisfied_by(password.as_str()) {
+                return Ok((password, counter));
+            }
+        }
+
+        Err(Error::PolicyNotSatisfied { attempts })
+    }
+
+    /// Renders the password of this profile using the given entropy, which
+    /// must be [`Profile::entropy_len`] bytes long.
+    #[cfg(feature = "std")]
+    pub(crate) fn render(&self, entropy: &Entropy) -> Result<crate::Password, Error> {
+        self.check_length()?;
+
+        let mut uninit_output = crate::uninit_vec(self.length);
+
+        self.render_to(entropy, &mut uninit_output)?;
+
+        // SAFETY: `uninit_output` was fully initialized in `render_to`.
+        Ok(unsafe { password_from_uninit(uninit_output) })
+    }
+
+    /// Returns the salt of the profile with the given counter.
+    #[cfg(feature = "std")]
+    pub(crate) fn salt(&self, counter: u64) -> std::vec::Vec<u8> {
+        crate::salt_vec(self.website, self.login, counter, self.strict)
+    }
+
+    /// Returns an [`Error`] if `counter` cannot be used by the profile.
+    pub(crate) fn check_counter(&self, counter: u64) -> Result<(), Error> {
+        if self.strict {
+            crate::check_strict_counter(counter)?;
+        } else if counter > u32::MAX.into() {
+            return Err(Error::CounterOutOfRange {
+                max: u32::MAX.into(),
+                got: counter,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Returns an [`Error`] if the length of the profile is not supported.
+    pub(crate) fn check_length(&self) -> Result<(), Error> {
+        let min = MIN_PASSWORD_LEN.max(self.alphabet.classes().len() + 1);
+        let max = if self.extended {
+            MAX_EXTENDED_PASSWORD_LEN
+        } else {
+            MAX_PASSWORD_LEN
+        };
+
+        if !(min..=max).contains(&self.length) {
+            return Err(Error::LengthOutOfRange {
+                min,
+                max,
+                got: self.length,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Returns the length of the entropy needed to render the password of
+    /// this profile.
+    pub(crate) fn entropy_len(&self) -> usize {
+        if self.length <= MAX_PASSWORD_LEN {
+            self.kdf.entropy_len()
+        } else {
+            self.kdf
+                .entropy_len()
+                .max(extended_entropy_len(self.alphabet, self.length))
+        }
+    }
+
+    /// Generates the entropy of the profile given its salt, and renders the
+    /// password to `output`, which must be exactly `self.length` bytes long.
+    fn generate_with_salt<'out>(
+        &self,
+        master_password: &str,
+        salt: &[u8],
+        output: &'out mut [MaybeUninit<u8>],
+    ) -> Result<&'out mut [u8], Error> {
+        let entropy = if self.length <= MAX_PASSWORD_LEN {
+            Entropy::generate(master_password, salt, &self.kdf, self.iterations)?
+        } else {
+            Entropy::generate_extended(
+                master_password,
+                salt,
+                &self.kdf,
+                self.iterations,
+                self.entropy_len(),
+            )?
+        };
+
+        self.render_to(&entropy, output)
+    }
+
+    /// Renders the password of the profile to `output`, which must be exactly
+    /// `self.length` bytes long.
+    fn render_to<'out>(
+        &self,
+        entropy: &Entropy,
+        output: &'out mut [MaybeUninit<u8>],
+    ) -> Result<&'out mut [u8], Error> {
+        if self.length <= MAX_PASSWORD_LEN {
+            try_render_password_to_uninit(entropy.as_bytes(), self.alphabet, output)
+        } else {
+            try_render_extended_password_to_uninit(entropy.as_bytes(), self.alphabet, output)
+        }
+    }
+}
+
+/// Converts a rendered password to a [`Password`](crate::Password).
+///
+/// # Safety
+///
+/// `output` must be fully initialized with ASCII characters.
+#[cfg(feature = "std")]
+unsafe fn password_from_uninit(output: std::vec::Vec<MaybeUninit<u8>>) -> crate::Password {
+    // SAFETY: `output` is fully initialized.
+    let output = unsafe { crate::assume_init_vec(output) };
+
+    // SAFETY: characters are all extracted from an `Alphabet`, which only
+    // contains ASCII characters.
+    let output = unsafe { std::string::String::from_utf8_unchecked(output) };
+
+    crate::Password::new(output)
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults() {
+        let profile = Profile::new("example.org", "contact@example.org");
+
+        assert_eq!(profile.counter(), 1);
+        assert_eq!(profile.length(), 16);
+        assert_eq!(profile.iterations(), 100_000);
+        assert!(profile.algorithm() == Algorithm::SHA256);
+        assert!(!profile.strict());
+        assert_eq!(
+            profile.alphabet(),
+            &Alphabet::from_charset(CharacterSet::All)
+        );
+    }
+
+    #[test]
+    fn generate_to_matches_generate() {
+        let profile = Profile::new("example.org", "contact@example.org")
+            .with_iterations(1_000)
+            .with_length(20);
+        let mut output = [0; 32];
+
+        assert_eq!(profile.generate_to("password", &mut output[..4]), Err(20));
+        assert_eq!(profile.generate_to("password", &mut output), Ok(20));
+        assert_eq!(
+            core::str::from_utf8(&output[..20]).unwrap(),
+            profile.generate("password").as_str(),
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        let profile = Profile::new("example.org", "contact@example.org").with_iterations(1);
+
+        assert_eq!(
+            profile.try_generate("").err(),
+            Some(Error::EmptyMasterPassword),
+        );
+        assert_eq!(
+            profile.with_iterations(0).try_generate("password").err(),
+            Some(Error::ZeroIterations),
+        );
+        assert_eq!(
+            profile.with_length(36).try_generate("password").err(),
+            Some(Error::LengthOutOfRange {
+                min: 5,
+                max: 35,
+                got: 36
+            }),
+        );
+        assert_eq!(
+            profile
+                .with_charset(CharacterSet::empty())
+                .try_generate("password")
+                .err(),
+            Some(Error::EmptyCharset),
+        );
+
+        let strict = profile
+            .with_counter(crate::MAX_STRICT_COUNTER + 1)
+            .with_strict(true);
+        let out_of_range = Error::CounterOutOfRange {
+            max: crate::MAX_STRICT_COUNTER,
+            got: crate::MAX_STRICT_COUNTER + 1,
+        };
+
+        assert_eq!(strict.try_generate("password").err(), Some(out_of_range));
+        assert_eq!(
+            strict.try_generate_to("password", &mut [0; 16]),
+            Err(out_of_range),
+        );
+        assert_eq!(
+            strict.with_strict(false).try_generate("password").err(),
+            Some(Error::CounterOutOfRange {
+                max: u32::MAX.into(),
+                got: crate::MAX_STRICT_COUNTER + 1,
+            }),
+        );
+        assert!(profile
+            .with_counter(u32::MAX.into())
+            .try_generate("password")
+            .is_ok());
+    }
+
+    #[test]
+    fn policy() {
+        let profile = Profile::new("example.org", "contact@example.org").with_iterations(1);
+        let policy = crate::PasswordPolicy::new()
+            .with_min_digits(3)
+            .with_letter_first(true)
+            .with_max_consecutive(1);
+        let (password, counter) = profile.generate_with_policy("password", &policy);
+
+        assert!(policy.is_satisfied_by(password.as_str()));
+        assert_eq!(
+            password.as_str(),
+            profile.with_counter(counter).generate("password").as_str(),
+        );
+
+        // The first counter that satisfies the policy is always returned.
+        for previous in profile.counter()..counter {
+            let password = profile.with_counter(previous).generate("password");
+
+            assert!(!policy.is_satisfied_by(password.as_str()));
+        }
+        assert_eq!(
+            profile
+                .with_counter(counter)
+                .generate_with_policy("password", &policy)
+                .1,
+            counter,
+        );
+
+        // A policy satisfied by the first password keeps the counter.
+        let (_, counter) = profile.generate_with_policy("password", &crate::PasswordPolicy::new());
+
+        assert_eq!(counter, profile.counter());
+    }
+
+    #[test]
+    fn policy_not_satisfied() {
+        let profile = Profile::new("example.org", "contact@example.org").with_iterations(1);
+        let policy = crate::PasswordPolicy::new();
+
+        assert_eq!(
+            profile
+                .try_generate_with_policy(
+                    "password",
+                    &policy.with_forbidden("abcdefghijklmnopqrstuvwxyz")
+                )
+                .err(),
+            Some(Error::PolicyNotSatisfied {
+                attempts: crate::MAX_POLICY_ATTEMPTS
+            }),
+        );
+        assert_eq!(
+            profile
+                .try_generate_with_policy("password", &policy.with_min_digits(17))
+                .err(),
+            Some(Error::PolicyNotSatisfied { attempts: 0 }),
+        );
+        assert_eq!(
+            profile
+                .try_generate_with_policy("password", &policy.with_max_len(12))
+                .err(),
+            Some(Error::LengthOutOfRange {
+                min: 5,
+                max: 12,
+                got: 16
+            }),
+        );
+        assert_eq!(
+            profile
+                .with_counter(u64::from(u32::MAX) - 1)
+                .try_generate_with_policy(
+                    "password",
+                    &policy.with_forbidden("abcdefghijklmnopqrstuvwxyz")
+                )
+                .err(),
+            Some(Error::PolicyNotSatisfied { attempts: 2 }),
+        );
+        assert_eq!(
+            profile
+                .with_counter(crate::MAX_STRICT_COUNTER - 1)
+                .with_strict(true)
+                .try_generate_with_policy(
+                    "password",
+                    &policy.with_forbidden("abcdefghijklmnopqrstuvwxyz")
+                )
+                .err(),
+            Some(Error::PolicyNotSatisfied { attempts: 2 }),
+        );
+        assert_eq!(
+            profile.try_generate_with_policy("", &policy).err(),
+            Some(Error::EmptyMasterPassword),
+        );
+    }
+
+    #[test]
+    fn extended() {
+        let profile = Profile::new("example.org", "contact@example.org").with_iterations(1_000);
+
+        // Extended mode does not change passwords that fit in the default mode.
+        assert_eq!(
+            profile.generate("password").as_str(),
+            profile.with_extended(true).generate("password").as_str(),
+        );
+
+        let password = profile
+            .with_extended(true)
+            .with_length(64)
+            .generate("password");
+        let mut output = [0; 64];
+
+        assert_eq!(password.as_str().len(), 64);
+        assert_eq!(
+            profile
+                .with_extended(true)
+                .with_length(64)
+                .try_generate_to("password", &mut output),
+            Ok(64),
+        );
+        assert_eq!(password.as_str().as_bytes(), &output[..]);
+        assert_eq!(
+            profile.with_length(64).try_generate("password").err(),
+            Some(Error::LengthOutOfRange {
+                min: 5,
+                max: 35,
+                got: 64
+            }),
+        );
+    }
+
+    #[test]
+    fn alphabet() {
+        let profile = Profile::new("example.org", "contact@example.org").with_iterations(1_000);
+        let alphabet = Alphabet::new(&[CharacterSet::LOWERCASE, "!@#$%"]).unwrap();
+
+        // A character set is equivalent to its alphabet.
+        assert_eq!(
+            profile
+                .with_charset(CharacterSet::Letters)
+                .generate("password")
+                .as_str(),
+            profile
+                .with_alphabet(Alphabet::from_charset(CharacterSet::Letters))
+                .generate("password")
+                .as_str(),
+        );
+
+        let password = profile.with_alphabet(alphabet).generate("password");
+
+        assert!(password.as_str().bytes().any(|c| c.is_ascii_lowercase()));
+        assert!(password.as_str().bytes().any(|c| b"!@#$%".contains(&c)));
+        assert!(password
+            .as_str()
+            .bytes()
+            .all(|c| c.is_ascii_lowercase() || b"!@#$%".contains(&c)));
+    }
+
+    #[test]
+    fn kdf() {
+        /// Derives entropy using PBKDF2 with twice as many iterations.
+        struct DoubleIterations;
+
+        impl Kdf for DoubleIterations {
+            fn entropy_len(&self) -> usize {
+                32
+            }
+
+            fn derive(
+                &self,
+                master_password: &str,
+                salt: &[u8],
+                iterations: u32,
+                output: &mut [u8],
+            ) -> Result<(), Error> {
+                Algorithm::SHA256.derive(master_password, salt, iterations * 2, output)
+            }
+        }
+
+        let profile = Profile::new("example.org", "contact@example.org").with_iterations(500);
+        let custom = profile.with_kdf(DoubleIterations);
+
+        assert_eq!(
+            custom.generate("password").as_str(),
+            profile.with_iterations(1_000).generate("password").as_str(),
+        );
+    }
+}
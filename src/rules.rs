@@ -0,0 +1,491 @@
+//! Parsing of password requirements written in the `passwordrules` format.
+
+use std::str::FromStr;
+use std::string::String;
+use std::vec::Vec;
+
+use crate::{Alphabet, CharacterSet, Error, PasswordPolicy, MAX_ALPHABET_CLASSES};
+
+/// The characters of the `special` class of the `passwordrules` format,
+/// excluding the space.
+const SPECIAL_CHARACTERS: &str = "-~!@#$%^&*_+=`|(){}[:;\\\"'<>,.?]";
+
+/// Password requirements parsed from the
+/// [`passwordrules`](https://developer.apple.com/password-rules/) format used
+/// by websites to describe the passwords they accept, e.g.
+/// `required: lower; required: digit; allowed: [-!]; max-consecutive: 2`.
+///
+/// Rules are converted to the settings of a [`Profile`](crate::Profile):
+///
+/// - Each `required` rule becomes a class of the [`Alphabet`], so that
+///   rendered passwords contain at least one of its characters. Characters
+///   of earlier `required` rules are removed from later ones, and characters
+///   that are only `allowed` form an additional class.
+/// - `minlength` and `maxlength` restrict the length of the password, see
+///   [`PasswordRules::length`].
+/// - `max-consecutive` is enforced by the [`PasswordPolicy`] returned by
+///   [`PasswordRules::policy`].
+///
+/// ```
+/// use lesspass::{PasswordRules, Profile};
+///
+/// let rules: PasswordRules = "required: lower; required: digit; allowed: [-!]; \
+///                             max-consecutive: 2; minlength: 8; maxlength: 12"
+///     .parse()
+///     .unwrap();
+///
+/// assert_eq!(rules.alphabet().classes(), ["abcdefghijklmnopqrstuvwxyz", "0123456789", "-!"]);
+/// assert_eq!(rules.length(16), 12);
+///
+/// let profile = Profile::new("example.org", "contact@example.org")
+///     .with_alphabet(rules.alphabet())
+///     .with_length(rules.length(16));
+/// let (password, counter) = profile.generate_with_policy("password", &rules.policy());
+///
+/// assert!(rules.policy().is_satisfied_by(password.as_str()));
+/// ```
+///
+/// Since passwords are rendered with printable ASCII characters only, the
+/// `unicode` class is treated like `ascii-printable`, and spaces and other
+/// characters are never used. Unknown properties are ignored, as required by
+/// the format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PasswordRules {
+    classes: Vec<String>,
+    max_consecutive: Option<usize>,
+    min_len: Option<usize>,
+    max_len: Option<usize>,
+}
+
+impl PasswordRules {
+    /// Returns the alphabet of the passwords satisfying the rules.
+    pub fn alphabet(&self) -> Alphabet<'_> {
+        let mut classes = [""; MAX_ALPHABET_CLASSES];
+
+        for (class, chars) in classes.iter_mut().zip(&self.classes) {
+            *class = chars;
+        }
+
+        Alphabet::new(&classes[..self.classes.len()]).expect("classes were validated when parsing")
+    }
+
+    /// Returns the policy enforcing the rules that cannot be expressed by the
+    /// alphabet and length of the password.
+    pub fn policy(&self) -> PasswordPolicy<'static> {
+        let mut policy = PasswordPolicy::new();
+
+        if let Some(max) = self.max_consecutive {
+            policy = policy.with_max_consecutive(max);
+        }
+        if let Some(max) = self.max_len {
+            policy = policy.with_max_len(max);
+        }
+
+        policy
+    }
+
+    /// Returns the minimum length of the password, if given by the rules.
+    pub fn min_len(&self) -> Option<usize> {
+        self.min_len
+    }
+
+    /// Returns the maximum length of the password, if given by the rules.
+    pub fn max_len(&self) -> Option<usize> {
+        self.max_len
+    }
+
+    /// Returns the length closest to `preferred` allowed by the rules.
+    ///
+    /// If the minimum length is greater than the maximum length, the maximum
+    /// length is returned.
+    pub fn length(&self, preferred: usize) -> usize {
+        let length = preferred.max(self.min_len.unwrap_or(0));
+
+        length.min(self.max_len.unwrap_or(usize::MAX))
+    }
+}
+
+impl FromStr for PasswordRules {
+    type Err = Error;
+
+    /// Parses rules in the `passwordrules` format.
+    ///
+    /// Returns [`Error::InvalidPasswordRules`] if the rules are malformed, and
+    /// [`Error::TooManyClasses`] if they require characters of more than
+    /// [`MAX_ALPHABET_CLASSES`] classes.
+    fn from_str(rules: &str) -> Result<Self, Error> {
+        let mut parser = Parser {
+            input: rules,
+            position: 0,
+        };
+        let mut required = Vec::<String>::new();
+        let mut allowed = String::new();
+        let mut max_consecutive = None;
+        let mut min_len = None;
+        let mut max_len = None;
+
+        loop {
+            parser.skip_whitespace();
+
+            if parser.rest().is_empty() {
+                break;
+            }
+            if parser.eat(';') {
+                continue;
+            }
+
+            let name = parser.identifier()?;
+
+            parser.skip_whitespace();
+            parser.expect(':')?;
+
+            if name.eq_ignore_ascii_case("required") {
+                parser.skip_whitespace();
+
+                let position = parser.position;
+                let chars = parser.classes()?;
+
+                if chars.is_empty() {
+                    return Err(Error::InvalidPasswordRules { position });
+                }
+
+                // Classes of an alphabet must be disjoint, so characters of
+                // earlier requirements are removed from this one, which is
+                // dropped if nothing remains.
+                let chars = chars
+                    .chars()
+                    .filter(|&c| !required.iter().any(|class| class.contains(c)))
+                    .collect::<String>();
+
+                if !chars.is_empty() {
+                    required.push(chars);
+                }
+            } else if name.eq_ignore_ascii_case("allowed") {
+                let chars = parser.classes()?;
+
+                push_unique(&mut allowed, &chars);
+            } else if name.eq_ignore_ascii_case("max-consecutive") {
+                let max = parser.integer()?;
+
+                max_consecutive = Some(max_consecutive.map_or(max, |m: usize| m.min(max)));
+            } else if name.eq_ignore_ascii_case("minlength") {
+                let min = parser.integer()?;
+
+                min_len = Some(min_len.map_or(min, |m: usize| m.max(min)));
+            } else if name.eq_ignore_ascii_case("maxlength") {
+                let max = parser.integer()?;
+
+                max_len = Some(max_len.map_or(max, |m: usize| m.min(max)));
+            } else {
+                parser.skip_value();
+            }
+
+            parser.skip_whitespace();
+
+            if !parser.rest().is_empty() {
+                parser.expect(';')?;
+            }
+        }
+
+        if required.is_empty() && allowed.is_empty() {
+            allowed = ascii_printable();
+        }
+
+        // Characters which are allowed but not required form their own class,
+        // so that they may appear in passwords.
+        let other_chars = allowed
+            .chars()
+            .filter(|&c| !required.iter().any(|class| class.contains(c)))
+            .collect::<String>();
+        let mut classes = required;
+
+        if !other_chars.is_empty() {
+            classes.push(other_chars);
+        }
+        if classes.len() > MAX_ALPHABET_CLASSES {
+            return Err(Error::TooManyClasses {
+                max: MAX_ALPHABET_CLASSES,
+                got: classes.len(),
+            });
+        }
+
+        Ok(Self {
+            classes,
+            max_consecutive,
+            min_len,
+            max_len,
+        })
+    }
+}
+
+/// A cursor over rules in the `passwordrules` format.
+struct Parser<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.position..]
+    }
+
+    fn error(&self) -> Error {
+        Error::InvalidPasswordRules {
+            position: self.position,
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.rest().starts_with(c);
+
+        if found {
+            self.position += c.len_utf8();
+        }
+
+        found
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), Error> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.error())
+        }
+    }
+
+    /// Parses a property or class name, made of letters and dashes.
+    fn identifier(&mut self) -> Result<&'a str, Error> {
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| !c.is_ascii_alphabetic() && c != '-')
+            .unwrap_or(rest.len());
+
+        if len == 0 {
+            return Err(self.error());
+        }
+
+        self.position += len;
+
+        Ok(&rest[..len])
+    }
+
+    /// Parses a non-negative integer.
+    fn integer(&mut self) -> Result<usize, Error> {
+        self.skip_whitespace();
+
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let value = rest[..len].parse().map_err(|_| self.error())?;
+
+        self.position += len;
+
+        Ok(value)
+    }
+
+    /// Parses a comma-separated list of classes, and returns the printable
+    /// ASCII characters they contain.
+    fn classes(&mut self) -> Result<String, Error> {
+        let mut chars = String::new();
+
+        loop {
+            self.skip_whitespace();
+
+            if self.eat('[') {
+                // `]` may appear in a custom class, as long as it is not the
+                // last character before a comma or the end of the rule.
+                let rest = self.rest();
+                let end = rest
+                    .match_indices(']')
+                    .map(|(i, _)| i)
+                    .find(|&i| {
+                        let after = rest[i + 1..].trim_start();
+
+                        after.is_empty() || after.starts_with([',', ';'])
+                    })
+                    .ok_or_else(|| self.error())?;
+                let custom = rest[..end]
+                    .chars()
+                    .filter(|c| c.is_ascii_graphic())
+                    .collect::<String>();
+
+                push_unique(&mut chars, &custom);
+                self.position += end + 1;
+            } else {
+                let position = self.position;
+                let name = self.identifier()?;
+                let class = match name.to_ascii_lowercase().as_str() {
+                    "upper" => String::from(CharacterSet::UPPERCASE),
+                    "lower" => String::from(CharacterSet::LOWERCASE),
+                    "digit" => String::from(CharacterSet::DIGITS),
+                    "special" => String::from(SPECIAL_CHARACTERS),
+                    "ascii-printable" | "unicode" => ascii_printable(),
+                    _ => return Err(Error::InvalidPasswordRules { position }),
+                };
+
+                push_unique(&mut chars, &class);
+            }
+
+            self.skip_whitespace();
+
+            if !self.eat(',') {
+                return Ok(chars);
+            }
+        }
+    }
+
+    /// Skips the value of an unknown property.
+    fn skip_value(&mut self) {
+        let rest = self.rest();
+
+        self.position += rest.find(';').unwrap_or(rest.len());
+    }
+}
+
+/// Returns all the printable ASCII characters, except the space.
+fn ascii_printable() -> String {
+    [
+        CharacterSet::LOWERCASE,
+        CharacterSet::UPPERCASE,
+        CharacterSet::DIGITS,
+        CharacterSet::SYMBOLS,
+    ]
+    .concat()
+}
+
+/// Appends the characters of `chars` which are not already in `class`.
+fn push_unique(class: &mut String, chars: &str) {
+    for c in chars.chars() {
+        if !class.contains(c) {
+            class.push(c);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(rules: &str) -> Result<PasswordRules, Error> {
+        rules.parse()
+    }
+
+    #[test]
+    fn classes() {
+        let rules = parse("required: lower; required: digit; allowed: [-!]").unwrap();
+
+        assert_eq!(
+            rules.alphabet().classes(),
+            [CharacterSet::LOWERCASE, CharacterSet::DIGITS, "-!"],
+        );
+
+        let rules = parse("required: upper, lower; allowed: lower, digit").unwrap();
+
+        assert_eq!(
+            rules.alphabet().classes(),
+            [
+                "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz",
+                CharacterSet::DIGITS,
+            ],
+        );
+
+        let rules = parse("Required: Special; REQUIRED: special").unwrap();
+
+        assert_eq!(rules.alphabet().classes(), [SPECIAL_CHARACTERS]);
+
+        // Earlier requirements are removed from later ones.
+        let rules = parse("required: upper; required: upper, lower; required: lower").unwrap();
+
+        assert_eq!(
+            rules.alphabet().classes(),
+            [CharacterSet::UPPERCASE, CharacterSet::LOWERCASE],
+        );
+
+        let rules = parse("required: [abc]; required: [bcd], digit").unwrap();
+
+        assert_eq!(rules.alphabet().classes(), ["abc", "d0123456789"]);
+
+        // Without requirements, all printable characters are allowed.
+        for rules in ["", " ; ", "foo: bar", "allowed: unicode"] {
+            assert_eq!(
+                parse(rules).unwrap().alphabet().classes(),
+                [ascii_printable()],
+                "{rules:?}",
+            );
+        }
+    }
+
+    #[test]
+    fn custom_classes() {
+        let rules = parse("required: [-]; required: []abc]; allowed: [ é],[]]").unwrap();
+
+        assert_eq!(rules.alphabet().classes(), ["-", "]abc"]);
+
+        let rules = parse("allowed: [a]b] , [c]").unwrap();
+
+        assert_eq!(rules.alphabet().classes(), ["a]bc"]);
+    }
+
+    #[test]
+    fn lengths() {
+        let rules = parse("minlength: 8; maxlength: 20; max-consecutive: 2").unwrap();
+
+        assert_eq!(rules.min_len(), Some(8));
+        assert_eq!(rules.max_len(), Some(20));
+        assert_eq!(rules.length(16), 16);
+        assert_eq!(rules.length(4), 8);
+        assert_eq!(rules.length(35), 20);
+        assert_eq!(
+            rules.policy(),
+            PasswordPolicy::new()
+                .with_max_consecutive(2)
+                .with_max_len(20)
+        );
+
+        // Repeated properties use the most restrictive value.
+        let rules = parse("minlength: 8; minlength: 10; maxlength: 20; maxlength: 30").unwrap();
+
+        assert_eq!(rules.min_len(), Some(10));
+        assert_eq!(rules.max_len(), Some(20));
+        assert_eq!(parse("").unwrap().length(16), 16);
+        assert_eq!(parse("").unwrap().policy(), PasswordPolicy::new());
+    }
+
+    #[test]
+    fn invalid() {
+        for (rules, position) in [
+            ("required", 8),
+            ("required lower", 9),
+            ("required: lowercase", 10),
+            ("required: lower digit", 16),
+            ("required: [abc", 11),
+            ("required: [é]", 10),
+            ("required: ,", 10),
+            ("minlength: -1", 11),
+            ("minlength: 8 8", 13),
+            ("maxlength: ", 11),
+            ("10", 0),
+        ] {
+            assert_eq!(
+                parse(rules),
+                Err(Error::InvalidPasswordRules { position }),
+                "{rules:?}",
+            );
+        }
+
+        assert_eq!(
+            parse(
+                "required: [a]; required: [b]; required: [c]; required: [d]; \
+                 required: [e]; required: [f]; required: [g]; required: [h]; allowed: [i]"
+            ),
+            Err(Error::TooManyClasses { max: 8, got: 9 }),
+        );
+    }
+}
@@ -0,0 +1,208 @@
+//! Types that hold secrets and overwrite their memory when dropped.
+//!
+//! None of these types implement `Debug`, `Display` or `Clone`, so that their
+//! contents can only be accessed (and copied) explicitly.
+
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::{
+    try_generate_entropy_to, try_generate_extended_entropy_to, Error, Kdf,
+    MAX_EXTENDED_ENTROPY_LEN, MIN_ENTROPY_LEN,
+};
+
+/// A master password, which is zeroed out when dropped.
+#[cfg(feature = "std")]
+pub struct MasterPassword(std::string::String);
+
+#[cfg(feature = "std")]
+impl MasterPassword {
+    /// Wraps the given master password, taking ownership of its buffer.
+    #[inline]
+    pub fn new(password: std::string::String) -> Self {
+        Self(password)
+    }
+
+    /// Returns the master password.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::string::String> for MasterPassword {
+    #[inline]
+    fn from(password: std::string::String) -> Self {
+        Self::new(password)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Drop for MasterPassword {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+#[cfg(feature = "std")]
+impl ZeroizeOnDrop for MasterPassword {}
+
+/// The entropy derived from a master password and a salt, which is zeroed out
+/// when dropped.
+///
+/// It can hold up to [`MAX_EXTENDED_ENTROPY_LEN`] bytes. With the `std`
+/// feature, the entropy is stored on the heap, so that moving an `Entropy`
+/// does not leave copies of it on the stack. Otherwise it is stored inline,
+/// and moving it may leave such copies which are never zeroed out; in this
+/// case, prefer the `*_to` functions of this crate (such as
+/// [`try_generate_entropy_to`]) with a buffer that is never moved.
+pub struct Entropy {
+    bytes: EntropyBytes,
+    len: usize,
+}
+
+/// The buffer of an [`Entropy`].
+#[cfg(feature = "std")]
+type EntropyBytes = std::boxed::Box<[u8; MAX_EXTENDED_ENTROPY_LEN]>;
+
+/// The buffer of an [`Entropy`].
+#[cfg(not(feature = "std"))]
+type EntropyBytes = [u8; MAX_EXTENDED_ENTROPY_LEN];
+
+impl Entropy {
+    /// Generates the entropy that corresponds to the given master password and
+    /// salt, using [`Kdf::entropy_len`] as length.
+    ///
+    /// See [`try_generate_entropy_to`] for more information.
+    pub fn generate(
+        master_password: &str,
+        salt: &[u8],
+        algorithm: impl Kdf,
+        iterations: u32,
+    ) -> Result<Self, Error> {
+        let mut entropy = Self::zeroed(algorithm.entropy_len());
+
+        try_generate_entropy_to(
+            master_password,
+            salt,
+            algorithm,
+            iterations,
+            entropy.as_mut_bytes(),
+        )?;
+
+        Ok(entropy)
+    }
+
+    /// Generates `len` bytes of entropy for the given master password and
+    /// salt, to be used in extended mode.
+    ///
+    /// See [`try_generate_extended_entropy_to`] for more information.
+    pub fn generate_extended(
+        master_password: &str,
+        salt: &[u8],
+        algorithm: impl Kdf,
+        iterations: u32,
+        len: usize,
+    ) -> Result<Self, Error> {
+        if !(MIN_ENTROPY_LEN..=MAX_EXTENDED_ENTROPY_LEN).contains(&len) {
+            return Err(Error::EntropyLengthOutOfRange {
+                min: MIN_ENTROPY_LEN,
+                max: MAX_EXTENDED_ENTROPY_LEN,
+                got: len,
+            });
+        }
+
+        let mut entropy = Self::zeroed(len);
+
+        try_generate_extended_entropy_to(
+            master_password,
+            salt,
+            algorithm,
+            iterations,
+            entropy.as_mut_bytes(),
+        )?;
+
+        Ok(entropy)
+    }
+
+    /// Copies the given entropy.
+    ///
+    /// Returns an [`Error`] if `bytes` is smaller than [`MIN_ENTROPY_LEN`] or
+    /// greater than [`MAX_EXTENDED_ENTROPY_LEN`].
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, Error> {
+        if !(MIN_ENTROPY_LEN..=MAX_EXTENDED_ENTROPY_LEN).contains(&bytes.len()) {
+            return Err(Error::EntropyLengthOutOfRange {
+                min: MIN_ENTROPY_LEN,
+                max: MAX_EXTENDED_ENTROPY_LEN,
+                got: bytes.len(),
+            });
+        }
+
+        let mut entropy = Self::zeroed(bytes.len());
+
+        entropy.as_mut_bytes().copy_from_slice(bytes);
+
+        Ok(entropy)
+    }
+
+    /// Returns `len` zero bytes of entropy.
+    #[inline]
+    #[cfg_attr(not(feature = "std"), allow(clippy::useless_conversion))]
+    pub(crate) fn zeroed(len: usize) -> Self {
+        debug_assert!(len <= MAX_EXTENDED_ENTROPY_LEN);
+
+        Self {
+            bytes: [0; MAX_EXTENDED_ENTROPY_LEN].into(),
+            len,
+        }
+    }
+
+    /// Returns the bytes of the entropy.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+
+    /// Returns the bytes of the entropy.
+    #[inline]
+    pub fn as_mut_bytes(&mut self) -> &mut [u8] {
+        &mut self.bytes[..self.len]
+    }
+}
+
+impl Drop for Entropy {
+    fn drop(&mut self) {
+        self.bytes[..].zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for Entropy {}
+
+/// A rendered password, which is zeroed out when dropped.
+#[cfg(feature = "std")]
+pub struct Password(std::string::String);
+
+#[cfg(feature = "std")]
+impl Password {
+    /// Wraps the given password, taking ownership of its buffer.
+    #[inline]
+    pub fn new(password: std::string::String) -> Self {
+        Self(password)
+    }
+
+    /// Returns the password.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(feature = "std")]
+impl Drop for Password {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+#[cfg(feature = "std")]
+impl ZeroizeOnDrop for Password {}
@@ -0,0 +1,273 @@
+//! Estimation of the strength of rendered passwords.
+
+use crate::{error, Alphabet, Error, MAX_EXTENDED_PASSWORD_LEN, MIN_PASSWORD_LEN};
+
+/// The strength of the passwords rendered with a given alphabet and length,
+/// returned by [`password_strength`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Strength {
+    bits: f64,
+}
+
+impl Strength {
+    /// Returns the entropy of rendered passwords, in bits.
+    ///
+    /// This is the (Shannon) entropy of the passwords themselves, assuming
+    /// that the entropy they are rendered from is uniformly distributed, i.e.
+    /// that the master password cannot be guessed more easily.
+    #[inline]
+    pub const fn bits(&self) -> f64 {
+        self.bits
+    }
+
+    /// Returns the average number of guesses needed to find a password by
+    /// brute force, i.e. `2^(bits - 1)`.
+    #[inline]
+    pub fn guesses(&self) -> f64 {
+        (self.bits - 1.0).exp2()
+    }
+
+    /// Returns the average time needed to find a password by brute force, in
+    /// seconds, for an attacker trying `guesses_per_second` passwords per
+    /// second.
+    ///
+    /// When each guess requires deriving entropy with PBKDF2, this is the
+    /// number of iterations the attacker computes per second, divided by the
+    /// number of iterations used to generate the password.
+    #[inline]
+    pub fn crack_time(&self, guesses_per_second: f64) -> f64 {
+        self.guesses() / guesses_per_second
+    }
+}
+
+/// Returns the strength of the passwords of `len` characters rendered with
+/// the given character set or alphabet, with
+/// [`render_password_to`](crate::render_password_to) or
+/// [`render_extended_password_to`](crate::render_extended_password_to).
+///
+/// Since at least one character of each class is inserted in the password,
+/// rendered passwords are slightly weaker than random strings of the same
+/// length; for instance, a 16-character password using
+/// [`CharacterSet::All`](crate::CharacterSet::All) has about 104.5 bits of
+/// entropy, against 104.9 bits for a random string of 94 characters.
+///
+/// ```
+/// use lesspass::{password_strength, CharacterSet};
+///
+/// let strength = password_strength(CharacterSet::All, 16);
+///
+/// assert!((104.4..104.5).contains(&strength.bits()));
+///
+/// // Without symbols, at 10 billion guesses per second.
+/// let strength = password_strength(CharacterSet::Letters | CharacterSet::Digits, 12);
+/// let years = strength.crack_time(1e10) / (365.25 * 24.0 * 3600.0);
+///
+/// assert!((71.1..71.2).contains(&strength.bits()));
+/// assert!((4_000.0..4_200.0).contains(&years));
+/// ```
+///
+/// Characters appearing several times in the alphabet are counted as
+/// distinct characters, which overestimates the strength of such alphabets.
+///
+/// # Panics
+///
+/// Panics if the alphabet or one of its classes is empty, or if `len` is not
+/// a valid length for this alphabet in extended mode. See
+/// [`try_password_strength`] for a version of this function that returns an
+/// [`Error`] instead.
+#[track_caller]
+#[inline]
+pub fn password_strength<'a>(charset: impl Into<Alphabet<'a>>, len: usize) -> Strength {
+    error::unwrap(try_password_strength(charset, len))
+}
+
+/// Same as [`password_strength`], but returns an [`Error`] instead of
+/// panicking if its inputs are invalid.
+pub fn try_password_strength<'a>(
+    charset: impl Into<Alphabet<'a>>,
+    len: usize,
+) -> Result<Strength, Error> {
+    let alphabet = charset.into();
+
+    if alphabet.classes().is_empty() {
+        return Err(Error::EmptyCharset);
+    }
+    if let Some(index) = (0..alphabet.classes().len()).find(|&i| alphabet.class_len(i) == 0) {
+        return Err(Error::EmptyClass { index });
+    }
+
+    let classes = alphabet.classes().len();
+    let min_len = MIN_PASSWORD_LEN.max(classes + 1);
+
+    if !(min_len..=MAX_EXTENDED_PASSWORD_LEN).contains(&len) {
+        return Err(Error::LengthOutOfRange {
+            min: min_len,
+            max: MAX_EXTENDED_PASSWORD_LEN,
+            got: len,
+        });
+    }
+
+    // Passwords are rendered by picking `initial_len` characters in the whole
+    // alphabet, and then inserting a character of each class at a random
+    // position. Characters are never inserted at the end, so the last
+    // character is always one of the initial ones.
+    let chars_len = alphabet.len() as f64;
+    let initial_len = len - classes;
+    let mut bits = initial_len as f64 * chars_len.log2();
+
+    for i in 0..classes {
+        let class_len = alphabet.class_len(i) as f64;
+
+        bits += class_len.log2() + ((initial_len + i) as f64).log2();
+
+        // A password with `m` characters of the class (excluding the last
+        // character) is rendered with any of them as the inserted one, i.e.
+        // `m` times more often. The number of characters of the class which
+        // are not inserted (and not last) follows a binomial distribution.
+        let other_chars = expected_log2(initial_len - 1, class_len / chars_len);
+
+        bits -= other_chars;
+    }
+
+    Ok(Strength { bits })
+}
+
+/// Returns `E[log2(X + 1)]`, where `X` follows a binomial distribution of
+/// `trials` trials with success probability `p`.
+fn expected_log2(trials: usize, p: f64) -> f64 {
+    let ln_p = p.ln();
+    let ln_q = (1.0 - p).ln();
+    let mut ln_binomial = 0.0;
+    let mut sum = 0.0;
+
+    for successes in 0..=trials {
+        let failures = trials - successes;
+
+        if successes > 0 {
+            ln_binomial += (failures as f64 + 1.0).ln() - (successes as f64).ln();
+        }
+
+        // Computed in log space since probabilities may underflow; `0 * ln(0)`
+        // must be 0 when `p` is 0 or 1.
+        let mut ln_probability = ln_binomial;
+
+        if successes > 0 {
+            ln_probability += successes as f64 * ln_p;
+        }
+        if failures > 0 {
+            ln_probability += failures as f64 * ln_q;
+        }
+
+        sum += ln_probability.exp() * (successes as f64 + 1.0).log2();
+    }
+
+    sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{render_password_to, CharacterSet};
+    use std::collections::HashMap;
+
+    /// Computes the entropy of the passwords rendered by `render_password_to`
+    /// by rendering all of them with all possible entropies.
+    fn exact_bits(classes: &[&str], len: usize) -> f64 {
+        let alphabet = Alphabet::new(classes).unwrap();
+        let initial_len = len - classes.len();
+        let renderings = (alphabet.len() as u32).pow(initial_len as u32)
+            * classes
+                .iter()
+                .map(|class| class.len() as u32)
+                .product::<u32>()
+            * (initial_len..len).map(|i| i as u32).product::<u32>();
+        let mut counts = HashMap::new();
+
+        // Entropies from 0 to `renderings` (excluded) map to every way of
+        // rendering a password exactly once.
+        for entropy in 0..renderings {
+            let mut bytes = [0; 32];
+            let mut password = std::vec![0; len];
+
+            bytes[28..].copy_from_slice(&entropy.to_be_bytes());
+            render_password_to(&bytes, alphabet, &mut password);
+            *counts.entry(password).or_insert(0) += 1;
+        }
+
+        counts
+            .values()
+            .map(|&count| {
+                let p = f64::from(count) / f64::from(renderings);
+
+                -p * p.log2()
+            })
+            .sum()
+    }
+
+    #[test]
+    fn matches_exact() {
+        for (classes, len) in [
+            (&["ab", "c"][..], 5),
+            (&["ab", "c"][..], 6),
+            (&["a", "bcd", "ef"][..], 5),
+            (&["abc"][..], 5),
+            (&["a", "b", "c", "d", "e"][..], 6),
+        ] {
+            let expected = exact_bits(classes, len);
+            let bits = password_strength(Alphabet::new(classes).unwrap(), len).bits();
+
+            assert!(
+                (bits - expected).abs() < 1e-9,
+                "{classes:?} {len}: {bits} != {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn charsets() {
+        assert!(
+            (password_strength(CharacterSet::Digits, 10).bits() - 10.0f64.log2() * 10.0).abs()
+                < 1e-9
+        );
+        assert!(password_strength(CharacterSet::All, 16).bits() < 16.0 * 94f64.log2());
+        assert!(
+            password_strength(CharacterSet::All, 512).bits()
+                > password_strength(CharacterSet::All, 35).bits()
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(
+            try_password_strength(CharacterSet::All, 4),
+            Err(Error::LengthOutOfRange {
+                min: 5,
+                max: 512,
+                got: 4
+            }),
+        );
+        assert_eq!(
+            try_password_strength(CharacterSet::All, 513),
+            Err(Error::LengthOutOfRange {
+                min: 5,
+                max: 512,
+                got: 513
+            }),
+        );
+        assert_eq!(
+            try_password_strength(
+                Alphabet::new(&["a", "b", "c", "d", "e", "f", "g", "h"]).unwrap(),
+                8
+            ),
+            Err(Error::LengthOutOfRange {
+                min: 9,
+                max: 512,
+                got: 8
+            }),
+        );
+        assert_eq!(
+            try_password_strength(Alphabet::new(&["ab", "01"]).unwrap().without_ambiguous(), 8),
+            Err(Error::EmptyClass { index: 1 }),
+        );
+    }
+}
@@ -0,0 +1,531 @@
+//! Generation of passwords with the legacy LessPass v1 algorithm.
+//!
+//! LessPass v1 derives a key from the master password and the login with
+//! PBKDF2-SHA256 and [`V1_ITERATIONS`] iterations, then computes the HMAC of
+//! the website and counter (written in decimal) keyed by that key written in
+//! hexadecimal. Each hexadecimal digit of the HMAC is then mapped to a
+//! character of the class given by a template such as `vcVCns`, which yields
+//! pronounceable passwords like `azYS7,olOL2]`.
+//!
+//! This algorithm is only provided to recover passwords created with
+//! LessPass v1; new passwords should be generated with [`Profile`].
+
+use core::mem::MaybeUninit;
+
+use pbkdf2::hmac::{Hmac, Mac as _};
+use pbkdf2::pbkdf2_hmac;
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+use crate::{error, slice_to_maybe_uninit_mut, slice_to_maybe_uninit_ref, CharacterSet, Error};
+
+#[cfg(doc)]
+use crate::Profile;
+
+/// The number of PBKDF2 iterations used by LessPass v1.
+pub const V1_ITERATIONS: u32 = 8192;
+
+/// The length of the entropy generated by LessPass v1, in bytes.
+pub const V1_ENTROPY_LEN: usize = 32;
+
+/// The maximum length of a password rendered by LessPass v1, inclusive, i.e.
+/// the number of hexadecimal digits of its entropy.
+pub const MAX_V1_PASSWORD_LEN: usize = 2 * V1_ENTROPY_LEN;
+
+/// The length of the passwords generated by LessPass v1 by default.
+pub const DEFAULT_V1_LENGTH: usize = 12;
+
+/// Generates the salt of a LessPass v1 password, i.e. the target website
+/// followed by the counter written in decimal, and writes it to `output`.
+///
+/// Returns `Ok(written_size)` if `output` is large enough, and
+/// `Err(required_size)` if it isn't (in which case nothing will be written).
+#[inline]
+pub fn generate_v1_salt_to(website: &str, counter: u32, output: &mut [u8]) -> Result<usize, usize> {
+    generate_v1_salt_to_uninit(website, counter, slice_to_maybe_uninit_mut(output)).map(|x| x.len())
+}
+
+/// Same as [`generate_v1_salt_to`], but works with an uninitialized output
+/// buffer, which is okay since it only writes to it, without reading from it.
+pub fn generate_v1_salt_to_uninit<'out>(
+    website: &str,
+    counter: u32,
+    output: &'out mut [MaybeUninit<u8>],
+) -> Result<&'out mut [u8], usize> {
+    let mut counter_buf = [MaybeUninit::uninit(); 10];
+    let counter = {
+        let mut counter = counter;
+        let mut i = counter_buf.len();
+
+        // Unlike LessPass v2, the counter 0 is written as "0".
+        loop {
+            counter_buf[i - 1].write(b'0' + (counter % 10) as u8);
+            counter /= 10;
+            i -= 1;
+
+            if counter == 0 {
+                break;
+            }
+        }
+
+        &counter_buf[i..]
+    };
+
+    let required_len = website.len() + counter.len();
+
+    if output.len() < required_len {
+        return Err(required_len);
+    }
+
+    output[..website.len()].copy_from_slice(slice_to_maybe_uninit_ref(website.as_bytes()));
+    output[website.len()..required_len].copy_from_slice(counter);
+
+    // SAFETY: all bytes up to `required_len` were written to (and thus
+    // initialized).
+    Ok(unsafe { &mut *(&mut output[..required_len] as *mut [MaybeUninit<u8>] as *mut [u8]) })
+}
+
+/// Same as [`generate_v1_salt_to`], but directly returns the salt instead of
+/// requiring a mutable output buffer.
+#[cfg(feature = "std")]
+pub fn generate_v1_salt(website: &str, counter: u32) -> std::vec::Vec<u8> {
+    let mut output = std::vec![0; website.len() + 10];
+    let len = generate_v1_salt_to(website, counter, &mut output)
+        .expect("the output is large enough for any counter");
+
+    output.truncate(len);
+    output
+}
+
+/// Generates the entropy of a LessPass v1 password using a previously
+/// computed salt (see [`generate_v1_salt_to`]), the login and the master
+/// password, and writes it to `output`.
+///
+/// # Panics
+///
+/// Panics if `master_password` or `login` is empty. See
+/// [`try_generate_v1_entropy_to`] for a version of this function that returns
+/// an [`Error`] instead.
+#[track_caller]
+#[inline]
+pub fn generate_v1_entropy_to(
+    master_password: &str,
+    login: &str,
+    salt: &[u8],
+    output: &mut [u8; V1_ENTROPY_LEN],
+) {
+    error::unwrap(try_generate_v1_entropy_to(
+        master_password,
+        login,
+        salt,
+        output,
+    ))
+}
+
+/// Same as [`generate_v1_entropy_to`], but returns an [`Error`] instead of
+/// panicking if its inputs are invalid.
+pub fn try_generate_v1_entropy_to(
+    master_password: &str,
+    login: &str,
+    salt: &[u8],
+    output: &mut [u8; V1_ENTROPY_LEN],
+) -> Result<(), Error> {
+    if master_password.is_empty() {
+        return Err(Error::EmptyMasterPassword);
+    }
+    if login.is_empty() {
+        return Err(Error::EmptyLogin);
+    }
+
+    let mut key = Zeroizing::new([0; 32]);
+    let mut hex_key = Zeroizing::new([0; 64]);
+
+    pbkdf2_hmac::<Sha256>(
+        master_password.as_bytes(),
+        login.as_bytes(),
+        V1_ITERATIONS,
+        &mut *key,
+    );
+
+    for (i, digit) in hex_key.iter_mut().enumerate() {
+        *digit = hex_digit(&*key, i);
+    }
+
+    // LessPass v1 uses the hexadecimal representation of the key as the key
+    // of the HMAC.
+    let mut mac = Hmac::<Sha256>::new_from_slice(&*hex_key)
+        .expect("Hmac's new_from_slice implementation is infallible");
+
+    mac.update(salt);
+    output.copy_from_slice(&mac.finalize().into_bytes());
+
+    Ok(())
+}
+
+/// Same as [`generate_v1_entropy_to`], but directly returns the entropy
+/// buffer instead of requiring a mutable output buffer.
+#[cfg(feature = "std")]
+#[track_caller]
+#[inline]
+pub fn generate_v1_entropy(
+    master_password: &str,
+    login: &str,
+    salt: &[u8],
+) -> [u8; V1_ENTROPY_LEN] {
+    error::unwrap(try_generate_v1_entropy(master_password, login, salt))
+}
+
+/// Same as [`generate_v1_entropy`], but returns an [`Error`] instead of
+/// panicking if its inputs are invalid.
+#[cfg(feature = "std")]
+pub fn try_generate_v1_entropy(
+    master_password: &str,
+    login: &str,
+    salt: &[u8],
+) -> Result<[u8; V1_ENTROPY_LEN], Error> {
+    let mut output = [0; V1_ENTROPY_LEN];
+
+    try_generate_v1_entropy_to(master_password, login, salt, &mut output)?;
+
+    Ok(output)
+}
+
+/// Returns the characters of the given class of a LessPass v1 template.
+///
+/// The classes are `v` and `V` (lowercase and uppercase vowels), `c` and `C`
+/// (lowercase and uppercase consonants), `a` (letters), `A` (uppercase
+/// letters), `n` (digits), `s` (symbols) and `x` (all of the above).
+const fn v1_class(class: u8) -> Option<&'static [u8]> {
+    Some(match class {
+        b'v' => b"aeiouy",
+        b'V' => b"AEIOUY",
+        b'c' => b"bcdfghjklmnpqrstvwxz",
+        b'C' => b"BCDFGHJKLMNPQRSTVWXZ",
+        b'a' => b"AEIOUYaeiouyBCDFGHJKLMNPQRSTVWXZbcdfghjklmnpqrstvwxz",
+        b'A' => b"AEIOUYBCDFGHJKLMNPQRSTVWXZ",
+        b'n' => b"0123456789",
+        b's' => b"@&%?,=[]_:-+*$#!'^~;()/.",
+        b'x' => b"AEIOUYaeiouyBCDFGHJKLMNPQRSTVWXZbcdfghjklmnpqrstvwxz0123456789@&%?,=[]_:-+*$#!'^~;()/.",
+        _ => return None,
+    })
+}
+
+impl CharacterSet {
+    /// Returns the LessPass v1 template that corresponds to this character
+    /// set, e.g. `vcVCns` for [`CharacterSet::All`].
+    ///
+    /// The template of the empty set is the empty string `""`.
+    pub const fn get_v1_template(self) -> &'static str {
+        match (
+            self.contains(Self::Lowercase),
+            self.contains(Self::Uppercase),
+            self.contains(Self::Digits),
+            self.contains(Self::Symbols),
+        ) {
+            (true, true, true, true) => "vcVCns",
+            (true, true, true, false) => "vcVCn",
+            (true, true, false, true) => "vcVCs",
+            (true, true, false, false) => "vcVC",
+
+            (true, false, true, true) => "vcns",
+            (true, false, true, false) => "vcn",
+            (true, false, false, true) => "vcs",
+            (true, false, false, false) => "vc",
+
+            (false, true, true, true) => "VCns",
+            (false, true, true, false) => "VCn",
+            (false, true, false, true) => "VCs",
+            (false, true, false, false) => "VC",
+
+            (false, false, true, true) => "ns",
+            (false, false, true, false) => "n",
+            (false, false, false, true) => "s",
+
+            _ => "",
+        }
+    }
+}
+
+/// Same as [`render_v1_password_to`], but works with an uninitialized output
+/// buffer, which is okay since it only writes to it, without reading from it.
+#[track_caller]
+#[inline]
+pub fn render_v1_password_to_uninit<'out>(
+    entropy: &[u8],
+    template: &str,
+    output: &'out mut [MaybeUninit<u8>],
+) -> &'out mut [u8] {
+    error::unwrap(try_render_v1_password_to_uninit(entropy, template, output))
+}
+
+/// Same as [`render_v1_password_to_uninit`], but returns an [`Error`] instead
+/// of panicking if its inputs are invalid.
+pub fn try_render_v1_password_to_uninit<'out>(
+    entropy: &[u8],
+    template: &str,
+    output: &'out mut [MaybeUninit<u8>],
+) -> Result<&'out mut [u8], Error> {
+    check_v1_render_args(entropy, template, output.len())?;
+
+    let template = template.as_bytes();
+
+    for (i, out) in output.iter_mut().enumerate() {
+        let class = v1_class(template[i % template.len()]).ok_or(Error::InvalidTemplate)?;
+        let digit = usize::from(hex_digit(entropy, i));
+
+        out.write(class[digit % class.len()]);
+    }
+
+    // SAFETY: all bytes of `output` were written to (and thus initialized).
+    Ok(unsafe { &mut *(output as *mut [MaybeUninit<u8>] as *mut [u8]) })
+}
+
+/// Renders a password of `output.len()` characters using the LessPass v1
+/// algorithm, the given entropy and template.
+///
+/// Each character of the template gives the class of the character at the
+/// same position (modulo the length of the template) in the password; see
+/// [`CharacterSet::get_v1_template`] for the templates used by LessPass v1.
+///
+/// ```
+/// use lesspass::{generate_v1_entropy, generate_v1_salt, render_v1_password, CharacterSet};
+///
+/// let salt = generate_v1_salt("lesspass.com", 1);
+/// let entropy = generate_v1_entropy("password", "contact@lesspass.com", &salt);
+/// let template = CharacterSet::All.get_v1_template();
+///
+/// assert_eq!(render_v1_password(&entropy, template, 12), "azYS7,olOL2]");
+/// ```
+///
+/// # Panics
+///
+/// Panics if `entropy` is empty, if `output` is empty or longer than twice
+/// the length of `entropy`, or if `template` is empty or contains a
+/// character that is not a class of LessPass v1. See
+/// [`try_render_v1_password_to`] for a version of this function that returns
+/// an [`Error`] instead.
+#[track_caller]
+#[inline]
+pub fn render_v1_password_to(entropy: &[u8], template: &str, output: &mut [u8]) {
+    error::unwrap(try_render_v1_password_to(entropy, template, output))
+}
+
+/// Same as [`render_v1_password_to`], but returns an [`Error`] instead of
+/// panicking if its inputs are invalid.
+#[inline]
+pub fn try_render_v1_password_to(
+    entropy: &[u8],
+    template: &str,
+    output: &mut [u8],
+) -> Result<(), Error> {
+    try_render_v1_password_to_uninit(entropy, template, slice_to_maybe_uninit_mut(output))
+        .map(|_| ())
+}
+
+/// Same as [`render_v1_password_to`], but directly returns the rendered
+/// password instead of requiring a mutable output buffer.
+#[cfg(feature = "std")]
+#[track_caller]
+#[inline]
+pub fn render_v1_password(entropy: &[u8], template: &str, len: usize) -> std::string::String {
+    error::unwrap(try_render_v1_password(entropy, template, len))
+}
+
+/// Same as [`render_v1_password`], but returns an [`Error`] instead of
+/// panicking if its inputs are invalid.
+#[cfg(feature = "std")]
+pub fn try_render_v1_password(
+    entropy: &[u8],
+    template: &str,
+    len: usize,
+) -> Result<std::string::String, Error> {
+    // Validate arguments before allocating the output buffer.
+    check_v1_render_args(entropy, template, len)?;
+
+    let mut uninit_output = crate::uninit_vec(len);
+
+    try_render_v1_password_to_uninit(entropy, template, &mut uninit_output)?;
+
+    // SAFETY: `uninit_output` was fully initialized in
+    // `try_render_v1_password_to_uninit`.
+    let output = unsafe { crate::assume_init_vec(uninit_output) };
+
+    // SAFETY: all classes only contain ASCII characters.
+    Ok(unsafe { std::string::String::from_utf8_unchecked(output) })
+}
+
+/// Generates the password of the given website, login and counter using the
+/// LessPass v1 algorithm, rendering `len` characters with `template`.
+///
+/// This is the same as calling [`generate_v1_salt`], [`generate_v1_entropy`]
+/// and [`render_v1_password`] in sequence:
+///
+/// ```
+/// use lesspass::{generate_v1_password, CharacterSet, DEFAULT_V1_LENGTH};
+///
+/// let template = CharacterSet::All.get_v1_template();
+/// let password = generate_v1_password(
+///     "lesspass.com",
+///     "contact@lesspass.com",
+///     "password",
+///     1,
+///     template,
+///     DEFAULT_V1_LENGTH,
+/// );
+///
+/// assert_eq!(password.as_str(), "azYS7,olOL2]");
+/// ```
+///
+/// # Panics
+///
+/// Panics in the same cases as [`generate_v1_entropy`] and
+/// [`render_v1_password`]. See [`try_generate_v1_password`] for a version of
+/// this function that returns an [`Error`] instead.
+#[cfg(feature = "std")]
+#[track_caller]
+pub fn generate_v1_password(
+    website: &str,
+    login: &str,
+    master_password: &str,
+    counter: u32,
+    template: &str,
+    len: usize,
+) -> crate::Password {
+    error::unwrap(try_generate_v1_password(
+        website,
+        login,
+        master_password,
+        counter,
+        template,
+        len,
+    ))
+}
+
+/// Same as [`generate_v1_password`], but returns an [`Error`] instead of
+/// panicking if its inputs are invalid.
+#[cfg(feature = "std")]
+pub fn try_generate_v1_password(
+    website: &str,
+    login: &str,
+    master_password: &str,
+    counter: u32,
+    template: &str,
+    len: usize,
+) -> Result<crate::Password, Error> {
+    let mut entropy = Zeroizing::new([0; V1_ENTROPY_LEN]);
+
+    // Validate the template and length before deriving the entropy.
+    check_v1_render_args(&*entropy, template, len)?;
+
+    let salt = generate_v1_salt(website, counter);
+
+    try_generate_v1_entropy_to(master_password, login, &salt, &mut entropy)?;
+
+    Ok(crate::Password::new(try_render_v1_password(
+        &*entropy, template, len,
+    )?))
+}
+
+/// Returns an [`Error`] if a password of length `len` cannot be rendered from
+/// `entropy` and `template`.
+fn check_v1_render_args(entropy: &[u8], template: &str, len: usize) -> Result<(), Error> {
+    if entropy.is_empty() {
+        return Err(Error::EmptyEntropy);
+    }
+    if template.is_empty() || !template.bytes().all(|c| v1_class(c).is_some()) {
+        return Err(Error::InvalidTemplate);
+    }
+
+    let max = 2 * entropy.len();
+
+    if !(1..=max).contains(&len) {
+        return Err(Error::LengthOutOfRange {
+            min: 1,
+            max,
+            got: len,
+        });
+    }
+
+    Ok(())
+}
+
+/// Returns the ASCII code of the `i`-th lowercase hexadecimal digit of
+/// `bytes`.
+fn hex_digit(bytes: &[u8], i: usize) -> u8 {
+    let byte = bytes[i / 2];
+    let nibble = if i % 2 == 0 { byte >> 4 } else { byte & 0xf };
+
+    b"0123456789abcdef"[usize::from(nibble)]
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn salt() {
+        assert_eq!(generate_v1_salt("example.org", 0), b"example.org0");
+        assert_eq!(generate_v1_salt("example.org", 1), b"example.org1");
+        assert_eq!(generate_v1_salt("example.org", 10), b"example.org10");
+        assert_eq!(generate_v1_salt("", u32::MAX), b"4294967295");
+
+        let mut output = [0; 12];
+
+        assert_eq!(generate_v1_salt_to("example.org", 10, &mut output), Err(13));
+        assert_eq!(generate_v1_salt_to("example.org", 9, &mut output), Ok(12));
+        assert_eq!(&output, b"example.org9");
+    }
+
+    #[test]
+    fn templates() {
+        assert_eq!(CharacterSet::All.get_v1_template(), "vcVCns");
+        assert_eq!(CharacterSet::Letters.get_v1_template(), "vcVC");
+        assert_eq!(CharacterSet::Digits.get_v1_template(), "n");
+        assert_eq!(CharacterSet::empty().get_v1_template(), "");
+    }
+
+    #[test]
+    fn render() {
+        // Entropy whose hexadecimal digits are "0123456789abcdef".
+        let entropy = [0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef];
+
+        // The ASCII codes of '0' and 'a' are 48 and 97.
+        assert_eq!(render_v1_password(&entropy, "n", 16), "8901234567789012");
+        assert_eq!(render_v1_password(&entropy, "v", 4), "aeio");
+        assert_eq!(render_v1_password(&entropy, "vC", 4), "aMiP");
+    }
+
+    #[test]
+    fn errors() {
+        let entropy = [0; V1_ENTROPY_LEN];
+
+        assert_eq!(
+            try_render_v1_password(&[], "vc", 12),
+            Err(Error::EmptyEntropy)
+        );
+        assert_eq!(
+            try_render_v1_password(&entropy, "", 12),
+            Err(Error::InvalidTemplate)
+        );
+        assert_eq!(
+            try_render_v1_password(&entropy, "vcX", 12),
+            Err(Error::InvalidTemplate)
+        );
+        assert_eq!(
+            try_render_v1_password(&entropy, "vc", 65),
+            Err(Error::LengthOutOfRange {
+                min: 1,
+                max: 64,
+                got: 65
+            })
+        );
+        assert_eq!(
+            try_generate_v1_entropy("", "login", b"salt"),
+            Err(Error::EmptyMasterPassword)
+        );
+        assert_eq!(
+            try_generate_v1_entropy("password", "", b"salt"),
+            Err(Error::EmptyLogin)
+        );
+    }
+}
@@ -9,13 +9,17 @@ pub fn generate(
     website: &str,
     login: &str,
     password: &str,
-    counter: u32,
+    counter: u64,
     length: usize,
     charset: CharacterSet,
 ) -> String {
-    let salt = generate_salt(website, login, counter);
-    let entropy = generate_entropy(password, &salt, Algorithm::SHA256, 100000);
-    render_password(&entropy, charset, length)
+    Profile::new(website, login)
+        .with_counter(counter)
+        .with_length(length)
+        .with_charset(charset)
+        .generate(password)
+        .as_str()
+        .to_owned()
 }
 
 fn t(
@@ -23,7 +27,7 @@ fn t(
     website: &str,
     login: &str,
     password: &str,
-    counter: u32,
+    counter: u64,
     length: usize,
     passwords: &[&str],
 ) {
@@ -0,0 +1,213 @@
+// Generates `v1.rs`, which tests the LessPass v1 algorithm of lesspass.rs.
+//
+// Passwords are computed with a transcription of the reference implementation
+// of LessPass v1 (`lesspass@1`), which is first checked against a vector of
+// its own test suite.
+//
+// Usage: node make-v1-tests.js > v1.rs
+
+"use strict";
+
+const crypto = require("crypto");
+
+const passwordsChars = {
+  V: "AEIOUY",
+  C: "BCDFGHJKLMNPQRSTVWXZ",
+  v: "aeiouy",
+  c: "bcdfghjklmnpqrstvwxz",
+  A: "AEIOUYBCDFGHJKLMNPQRSTVWXZ",
+  a: "AEIOUYaeiouyBCDFGHJKLMNPQRSTVWXZbcdfghjklmnpqrstvwxz",
+  n: "0123456789",
+  s: "@&%?,=[]_:-+*$#!'^~;()/.",
+  x: "AEIOUYaeiouyBCDFGHJKLMNPQRSTVWXZbcdfghjklmnpqrstvwxz0123456789@&%?,=[]_:-+*$#!'^~;()/.",
+};
+
+function encryptLogin(login, masterPassword) {
+  return crypto.pbkdf2Sync(masterPassword, login, 8192, 32, "sha256").toString("hex");
+}
+
+function deriveEncryptedLogin(encryptedLogin, site, passwordOptions) {
+  const salt = site + passwordOptions.counter.toString();
+  const derivedHash = crypto.createHmac("sha256", encryptedLogin).update(salt).digest("hex");
+
+  return derivedHash.substring(0, passwordOptions.length);
+}
+
+function getPasswordTemplate(passwordTypes) {
+  const templates = { lowercase: "vc", uppercase: "VC", numbers: "n", symbols: "s" };
+
+  return Object.keys(templates)
+    .filter((template) => passwordTypes[template])
+    .map((template) => templates[template])
+    .join("");
+}
+
+function prettyPrint(hash, template) {
+  let password = "";
+
+  for (let index = 0; index < hash.length; index++) {
+    const passwordChar = passwordsChars[template[index % template.length]];
+
+    password += passwordChar[hash.charCodeAt(index) % passwordChar.length];
+  }
+
+  return password;
+}
+
+function generatePassword(site, login, masterPassword, passwordOptions) {
+  const encryptedLogin = encryptLogin(login, masterPassword);
+  const template = passwordOptions.template || getPasswordTemplate(passwordOptions);
+
+  return prettyPrint(deriveEncryptedLogin(encryptedLogin, site, passwordOptions), template);
+}
+
+function rustString(s) {
+  return JSON.stringify(s);
+}
+
+// Vectors of the test suite of LessPass v1, which are also checked as is in
+// the `reference_vectors` test.
+const all = { lowercase: true, uppercase: true, numbers: true, symbols: true };
+const reference = generatePassword("lesspass.com", "contact@lesspass.com", "password", {
+  ...all,
+  length: 12,
+  counter: 1,
+});
+
+if (reference !== "azYS7,olOL2]") {
+  throw new Error(`transcription mismatch: ${reference} !== azYS7,olOL2]`);
+}
+
+const referenceLogins = [
+  ["test@lesspass.com", "90cff82b8847525370a8f29a59ecf45db62c719a535788ad0df58d32304e925d"],
+  ["test@example.org", "d8af5f918db6b65b1db3d3984e5a400e39e1dbb19462220e4431de283809f472"],
+];
+
+for (const [login, expected] of referenceLogins) {
+  const encryptedLogin = encryptLogin(login, "password");
+
+  if (encryptedLogin !== expected) {
+    throw new Error(`transcription mismatch: ${encryptedLogin} !== ${expected}`);
+  }
+}
+
+const charsets = [
+  ["All", all],
+  ["Letters", { lowercase: true, uppercase: true }],
+  ["Lowercase", { lowercase: true }],
+  ["Uppercase | CharacterSet::Digits", { uppercase: true, numbers: true }],
+  ["Digits", { numbers: true }],
+  ["Symbols", { symbols: true }],
+];
+const cases = [
+  ["lesspass.com", "contact@lesspass.com", "password", 1, 12],
+  ["example.org", "contact@example.org", "password", 1, 12],
+  ["example.org", "contact@example.org", "password", 0, 12],
+  ["example.org", "contact@example.org", "password", 2, 12],
+  ["example.org", "contact@example.org", "password", 10, 16],
+  ["example.org", "user", "foobar", 1, 6],
+  ["example.org", "user", "foobar", 100000, 64],
+  ["", "❤", "I ❤ LessPass", 4294967295, 32],
+];
+
+console.log(`//! Tests the LessPass v1 algorithm.
+//!
+//! The test vectors in this file are generated by the script
+//! \`make-v1-tests.js\` located in the same directory as this test.
+use lesspass::*;
+
+fn t(
+    website: &str,
+    login: &str,
+    password: &str,
+    counter: u32,
+    length: usize,
+    passwords: &[(CharacterSet, &str)],
+) {
+    for &(charset, expected) in passwords {
+        let template = charset.get_v1_template();
+
+        assert_eq!(
+            generate_v1_password(website, login, password, counter, template, length).as_str(),
+            expected,
+            "Mismatch for website: {website:?}, login: {login:?}, password: {password:?}, \\
+             counter: {counter:?}, length: {length:?}, template: {template:?}",
+        );
+    }
+}
+
+/// Vectors copied from the test suite of LessPass v1, unlike the vectors below
+/// which are computed by a transcription of its implementation.
+#[test]
+fn reference_vectors() {
+    // \`generatePassword\`.
+    assert_eq!(
+        generate_v1_password(
+            "lesspass.com",
+            "contact@lesspass.com",
+            "password",
+            1,
+            CharacterSet::All.get_v1_template(),
+            DEFAULT_V1_LENGTH,
+        )
+        .as_str(),
+        "azYS7,olOL2]",
+    );
+
+    // \`_encryptLogin\`, the key derivation of LessPass v1.
+    for (login, expected) in [${referenceLogins
+      .map(
+        ([login, expected]) =>
+          `\n        (\n            ${rustString(login)},\n            ${rustString(expected)},\n        ),`,
+      )
+      .join("")}
+    ] {
+        let mut key = [0; V1_ENTROPY_LEN];
+
+        generate_entropy_to(
+            "password",
+            login.as_bytes(),
+            Algorithm::SHA256,
+            V1_ITERATIONS,
+            &mut key,
+        );
+
+        let key = key.iter().map(|b| format!("{b:02x}")).collect::<String>();
+
+        assert_eq!(key, expected, "Mismatch for login: {login:?}");
+    }
+}`);
+
+cases.forEach(([site, login, masterPassword, counter, length], i) => {
+  console.log();
+  console.log("#[test]");
+  console.log(`fn vectors_${String(i).padStart(3, "0")}() {`);
+  console.log("    t(");
+  console.log(`        ${rustString(site)},`);
+  console.log(`        ${rustString(login)},`);
+  console.log(`        ${rustString(masterPassword)},`);
+  console.log(`        ${counter},`);
+  console.log(`        ${length},`);
+  console.log("        &[");
+
+  for (const [name, options] of charsets) {
+    const expected = generatePassword(site, login, masterPassword, {
+      ...options,
+      length,
+      counter,
+    });
+
+    const args = [`CharacterSet::${name}`, rustString(expected)];
+
+    // Split long tuples the same way as rustfmt.
+    if (args.join(", ").length > 60) {
+      console.log(`            (\n${args.map((arg) => `                ${arg},\n`).join("")}            ),`);
+    } else {
+      console.log(`            (${args.join(", ")}),`);
+    }
+  }
+
+  console.log("        ],");
+  console.log("    );");
+  console.log("}");
+});
@@ -0,0 +1,101 @@
+//! Tests the strict LessPass compatibility mode on edge cases of the salt.
+//!
+//! The test vectors in this file are generated by the script
+//! `make-strict-tests.js` located in the same directory as this test.
+use lesspass::*;
+
+fn t(website: &str, login: &str, counter: u64, expected: &str) {
+    let salt = generate_strict_salt(website, login, counter);
+    let entropy = generate_entropy("password", &salt, Algorithm::SHA256, 100_000);
+
+    assert_eq!(
+        render_password(&entropy, CharacterSet::All, 16),
+        expected,
+        "Mismatch for website: {website:?}, login: {login:?}, counter: {counter:?}",
+    );
+
+    let profile = Profile::new(website, login)
+        .with_counter(counter)
+        .with_strict(true);
+
+    assert_eq!(profile.generate("password").as_str(), expected);
+}
+
+#[test]
+fn strict_000() {
+    t("example.org", "user@example.org", 0, "^2t+pIXBuY'Nyy?V");
+}
+
+#[test]
+fn strict_001() {
+    t(
+        "example.org",
+        "user@example.org",
+        4294967295,
+        "sDV{Cw`Z0/i3:Xsj",
+    );
+}
+
+#[test]
+#[cfg_attr(debug_assertions, ignore)]
+fn strict_002() {
+    t(
+        "example.org",
+        "user@example.org",
+        4294967296,
+        "Bv)y,2(33@4TQ*a%",
+    );
+}
+
+#[test]
+#[cfg_attr(debug_assertions, ignore)]
+fn strict_003() {
+    t(
+        "example.org",
+        "user@example.org",
+        9007199254740991,
+        "?[.tjMl_op,D}0Ba",
+    );
+}
+
+#[test]
+#[cfg_attr(debug_assertions, ignore)]
+fn strict_004() {
+    t("example.org", "", 1, "61!#78\"R-3+z&fTD");
+}
+
+#[test]
+#[cfg_attr(debug_assertions, ignore)]
+fn strict_005() {
+    t("example.org", "", 0, "Y^7c/lsnucb1U\"46");
+}
+
+#[test]
+#[cfg_attr(debug_assertions, ignore)]
+fn strict_006() {
+    t("example.org", " ", 1, "e-ZYOi_EOZ^s4i~X");
+}
+
+#[test]
+#[cfg_attr(debug_assertions, ignore)]
+fn strict_007() {
+    t("example.org", "\t \n", 1, "<(a=~)fv*QY7Bd'c");
+}
+
+#[test]
+#[cfg_attr(debug_assertions, ignore)]
+fn strict_008() {
+    t("example.org", " user@example.org ", 1, "uevo:uKstK98]-*f");
+}
+
+#[test]
+#[cfg_attr(debug_assertions, ignore)]
+fn strict_009() {
+    t("", "", 0, "-R0Y8\\8`y~J`c}5k");
+}
+
+#[test]
+#[cfg_attr(debug_assertions, ignore)]
+fn strict_010() {
+    t("", "", 1, "&=3k_r\"$W`$^F82k");
+}
@@ -0,0 +1,258 @@
+//! Tests the LessPass v1 algorithm.
+//!
+//! The test vectors in this file are generated by the script
+//! `make-v1-tests.js` located in the same directory as this test.
+use lesspass::*;
+
+fn t(
+    website: &str,
+    login: &str,
+    password: &str,
+    counter: u32,
+    length: usize,
+    passwords: &[(CharacterSet, &str)],
+) {
+    for &(charset, expected) in passwords {
+        let template = charset.get_v1_template();
+
+        assert_eq!(
+            generate_v1_password(website, login, password, counter, template, length).as_str(),
+            expected,
+            "Mismatch for website: {website:?}, login: {login:?}, password: {password:?}, \
+             counter: {counter:?}, length: {length:?}, template: {template:?}",
+        );
+    }
+}
+
+/// Vectors copied from the test suite of LessPass v1, unlike the vectors below
+/// which are computed by a transcription of its implementation.
+#[test]
+fn reference_vectors() {
+    // `generatePassword`.
+    assert_eq!(
+        generate_v1_password(
+            "lesspass.com",
+            "contact@lesspass.com",
+            "password",
+            1,
+            CharacterSet::All.get_v1_template(),
+            DEFAULT_V1_LENGTH,
+        )
+        .as_str(),
+        "azYS7,olOL2]",
+    );
+
+    // `_encryptLogin`, the key derivation of LessPass v1.
+    for (login, expected) in [
+        (
+            "test@lesspass.com",
+            "90cff82b8847525370a8f29a59ecf45db62c719a535788ad0df58d32304e925d",
+        ),
+        (
+            "test@example.org",
+            "d8af5f918db6b65b1db3d3984e5a400e39e1dbb19462220e4431de283809f472",
+        ),
+    ] {
+        let mut key = [0; V1_ENTROPY_LEN];
+
+        generate_entropy_to(
+            "password",
+            login.as_bytes(),
+            Algorithm::SHA256,
+            V1_ITERATIONS,
+            &mut key,
+        );
+
+        let key = key.iter().map(|b| format!("{b:02x}")).collect::<String>();
+
+        assert_eq!(key, expected, "Mismatch for login: {login:?}");
+    }
+}
+
+#[test]
+fn vectors_000() {
+    t(
+        "lesspass.com",
+        "contact@lesspass.com",
+        "password",
+        1,
+        12,
+        &[
+            (CharacterSet::All, "azYS7,olOL2]"),
+            (CharacterSet::Letters, "azYSeqOLolAT"),
+            (CharacterSet::Lowercase, "azyseqololat"),
+            (
+                CharacterSet::Uppercase | CharacterSet::Digits,
+                "AZ3AW2OL7AD5",
+            ),
+            (CharacterSet::Digits, "493472787825"),
+            (CharacterSet::Symbols, "[?=[&,:@:@[]"),
+        ],
+    );
+}
+
+#[test]
+fn vectors_001() {
+    t(
+        "example.org",
+        "contact@example.org",
+        "password",
+        1,
+        12,
+        &[
+            (CharacterSet::All, "izIS5@ozYM2?"),
+            (CharacterSet::Letters, "izISelOZymAP"),
+            (CharacterSet::Lowercase, "iziselozymap"),
+            (
+                CharacterSet::Uppercase | CharacterSet::Digits,
+                "IZ0AT8OZ3ED1",
+            ),
+            (CharacterSet::Digits, "090458193921"),
+            (CharacterSet::Symbols, "%?%[]@??=&[?"),
+        ],
+    );
+}
+
+#[test]
+fn vectors_002() {
+    t(
+        "example.org",
+        "contact@example.org",
+        "password",
+        0,
+        12,
+        &[
+            (CharacterSet::All, "apUP9_osEB8]"),
+            (CharacterSet::Letters, "apUPevOSebIT"),
+            (CharacterSet::Lowercase, "apupevosebit"),
+            (
+                CharacterSet::Uppercase | CharacterSet::Digits,
+                "AP2OM6OS7UX5",
+            ),
+            (CharacterSet::Digits, "812196147085"),
+            (CharacterSet::Symbols, "@?,?&_?[&,%]"),
+        ],
+    );
+}
+
+#[test]
+fn vectors_003() {
+    t(
+        "example.org",
+        "contact@example.org",
+        "password",
+        2,
+        12,
+        &[
+            (CharacterSet::All, "ybUC2%awUN7]"),
+            (CharacterSet::Letters, "ybUCanAWunOT"),
+            (CharacterSet::Lowercase, "ybucanawunot"),
+            (
+                CharacterSet::Uppercase | CharacterSet::Digits,
+                "YB2YD0AW0IW5",
+            ),
+            (CharacterSet::Digits, "302120270075"),
+            (CharacterSet::Symbols, "=,,=[%[&,%:]"),
+        ],
+    );
+}
+
+#[test]
+fn vectors_004() {
+    t(
+        "example.org",
+        "contact@example.org",
+        "password",
+        10,
+        16,
+        &[
+            (CharacterSet::All, "emIN5[ecAP9=arYL"),
+            (CharacterSet::Letters, "emINedECapOCarYL"),
+            (CharacterSet::Lowercase, "eminedecapocaryl"),
+            (
+                CharacterSet::Uppercase | CharacterSet::Digits,
+                "EM6IT2EC4OZ1AR1A",
+            ),
+            (CharacterSet::Digits, "9960527141914318"),
+            (CharacterSet::Symbols, "&&_%][&=[??=[==@"),
+        ],
+    );
+}
+
+#[test]
+fn vectors_005() {
+    t(
+        "example.org",
+        "user",
+        "foobar",
+        1,
+        6,
+        &[
+            (CharacterSet::All, "enOW1]"),
+            (CharacterSet::Letters, "enOWot"),
+            (CharacterSet::Lowercase, "enowot"),
+            (CharacterSet::Uppercase | CharacterSet::Digits, "EN9OP5"),
+            (CharacterSet::Digits, "509715"),
+            (CharacterSet::Symbols, "]%?:?]"),
+        ],
+    );
+}
+
+#[test]
+fn vectors_006() {
+    t(
+        "example.org",
+        "user",
+        "foobar",
+        100000,
+        64,
+        &[
+            (
+                CharacterSet::All,
+                "ewAP6[yrOM2[inOT6%iwIM9?ixAW2%osON6_orUW0%azYB0,osOL7%ypAR0[ypOD",
+            ),
+            (
+                CharacterSet::Letters,
+                "ewAPisYRomASinOTixIWimOZixAWunOSonIVorUWixAZybIBosOLoxYParUDypOD",
+            ),
+            (
+                CharacterSet::Lowercase,
+                "ewapisyromasinotixiwimozixawunosonivoruwixazybibosoloxyparudypod",
+            ),
+            (
+                CharacterSet::Uppercase | CharacterSet::Digits,
+                "EW8OV4YR1ED4IN9EV8IW0EZ9IX8OQ0OS1IV6OR0EN8AZ3UN0OS1AW8YP2YB2YP9A",
+            ),
+            (
+                CharacterSet::Digits,
+                "5781641319246095686709996887201410669307082930001418783123021192",
+            ),
+            (
+                CharacterSet::Symbols,
+                "]&@?_[==?&[[_%?]_%_&%&??_%@:,%?[?%__?=,&%%[?=,%,?[?@:%=?[=,[=??[",
+            ),
+        ],
+    );
+}
+
+#[test]
+fn vectors_007() {
+    t(
+        "",
+        "❤",
+        "I ❤ LessPass",
+        4294967295,
+        32,
+        &[
+            (CharacterSet::All, "ixIQ0=ilUT5&ytOL8&ebAL0&alYD7%ix"),
+            (CharacterSet::Letters, "ixIQurILutEWytOLawEBalIMalYDonIX"),
+            (CharacterSet::Lowercase, "ixiqurilutewytolawebalimalydonix"),
+            (
+                CharacterSet::Uppercase | CharacterSet::Digits,
+                "IX6UB3IL2ET7YT1AL7EB2AN9AL3AW0IX",
+            ),
+            (CharacterSet::Digits, "68620308255715188770280948327008"),
+            (CharacterSet::Symbols, "_%_,,=%@,]]&=]?@@&&,[@%&[@=[:%%%"),
+        ],
+    );
+}